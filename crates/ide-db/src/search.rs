@@ -15,7 +15,7 @@ use memchr::memmem::Finder;
 use nohash_hasher::IntMap;
 use once_cell::unsync::Lazy;
 use parser::SyntaxKind;
-use syntax::{ast, match_ast, AstNode, AstToken, SyntaxElement, TextRange, TextSize};
+use syntax::{ast, match_ast, AstNode, AstToken, SmolStr, SyntaxElement, TextRange, TextSize};
 use triomphe::Arc;
 
 use crate::{
@@ -131,6 +131,8 @@ pub enum ReferenceCategory {
     Write,
     Read,
     Import,
+    /// The referenced local is moved, currently only used for closure captures.
+    Move,
     // FIXME: Some day should be able to search in doc comments. Would probably
     // need to switch from enum to bitflags then?
     // DocComment
@@ -379,6 +381,7 @@ impl Definition {
             scope: None,
             include_self_kw_refs: None,
             search_self_mod: false,
+            alias: None,
         }
     }
 }
@@ -394,6 +397,8 @@ pub struct FindUsages<'a> {
     include_self_kw_refs: Option<hir::Type>,
     /// whether to search for the `self` module
     search_self_mod: bool,
+    /// the name the definition is imported as, to search for instead of its own name
+    alias: Option<SmolStr>,
 }
 
 impl<'a> FindUsages<'a> {
@@ -404,6 +409,13 @@ impl<'a> FindUsages<'a> {
         self
     }
 
+    /// Search for the uses of the definition through `alias`, the name it is imported as by a
+    /// `use foo as alias;`, instead of its own name.
+    pub fn with_alias(mut self, alias: SmolStr) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
     /// Limit the search to a given [`SearchScope`].
     pub fn in_scope(self, scope: &'a SearchScope) -> Self {
         self.set_scope(Some(scope))
@@ -449,6 +461,7 @@ impl<'a> FindUsages<'a> {
         };
 
         let name = match self.def {
+            _ if self.alias.is_some() => self.alias.clone(),
             // special case crate modules as these do not have a proper name
             Definition::Module(module) if module.is_crate_root() => {
                 // FIXME: This assumes the crate name is always equal to its display name when it
@@ -874,9 +887,16 @@ fn def_to_ty(sema: &Semantics<'_, RootDatabase>, def: &Definition) -> Option<hir
 
 impl ReferenceCategory {
     fn new(def: &Definition, r: &ast::NameRef) -> Option<ReferenceCategory> {
-        // Only Locals and Fields have accesses for now.
-        if !matches!(def, Definition::Local(_) | Definition::Field(_)) {
-            return is_name_ref_in_import(r).then_some(ReferenceCategory::Import);
+        if is_name_ref_in_import(r) {
+            return Some(ReferenceCategory::Import);
+        }
+        // A const can't be written to, `&mut C` borrows a fresh temporary.
+        if matches!(def, Definition::Const(_)) {
+            return Some(ReferenceCategory::Read);
+        }
+        // Only Locals, Fields and Statics have accesses for now.
+        if !matches!(def, Definition::Local(_) | Definition::Field(_) | Definition::Static(_)) {
+            return None;
         }
 
         let mode = r.syntax().ancestors().find_map(|node| {
@@ -894,12 +914,22 @@ impl ReferenceCategory {
                     }
                     Some(ReferenceCategory::Read)
                 },
+                ast::RefExpr(expr) => {
+                    // A mutable borrow of the variable or field itself allows writing to it.
+                    let operand = expr.expr()?;
+                    if expr.mut_token().is_some()
+                        && operand.syntax().text_range().end() == r.syntax().text_range().end()
+                    {
+                        return Some(ReferenceCategory::Write);
+                    }
+                    None
+                },
                 _ => None
             }
         }
     });
 
-        // Default Locals and Fields to read
+        // Default to read
         mode.or(Some(ReferenceCategory::Read))
     }
 }
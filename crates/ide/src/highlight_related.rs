@@ -1,32 +1,105 @@
-use std::iter;
+use std::{iter, ops::ControlFlow};
 
-use hir::{DescendPreference, Semantics};
+use either::Either;
+use hir::{
+    CaptureKind, ClosureCapture, DescendPreference, GenericParam, HasSource, ModuleDef,
+    PathResolution, Semantics,
+};
 use ide_db::{
-    base_db::{FileId, FilePosition, FileRange},
-    defs::{Definition, IdentClass},
+    base_db::{salsa::Database, CrateOrigin, FileId, FilePosition, FileRange, LangCrateOrigin},
+    defs::{Definition, IdentClass, NameRefClass},
+    documentation::docs_with_rangemap,
+    famous_defs::FamousDefs,
     helpers::pick_best_token,
     search::{FileReference, ReferenceCategory, SearchScope},
-    syntax_helpers::node_ext::{
-        for_each_break_and_continue_expr, for_each_tail_expr, full_path_of_name_ref, walk_expr,
+    syntax_helpers::{
+        format_string::{lex_format_specifiers, FormatSpecifier},
+        node_ext::{
+            for_each_break_and_continue_expr, for_each_tail_expr, full_path_of_name_ref, walk_expr,
+        },
     },
     FxHashSet, RootDatabase,
 };
+use itertools::Itertools;
 use syntax::{
-    ast::{self, HasLoopBody},
-    match_ast, AstNode,
-    SyntaxKind::{self, IDENT, INT_NUMBER},
-    SyntaxToken, TextRange, T,
+    algo::find_node_at_range,
+    ast::{self, HasAttrs, HasLoopBody, HasName},
+    match_ast, AstNode, AstToken, Direction,
+    SyntaxKind::{self, IDENT, INT_NUMBER, LIFETIME_IDENT},
+    SyntaxNode, SyntaxToken, TextRange, TextSize, TokenAtOffset, WalkEvent, T,
 };
 
-use crate::{navigation_target::ToNav, NavigationTarget, TryToNav};
+use crate::{
+    doc_links::{doc_attributes, extract_definitions_from_docs, resolve_doc_path_for_def},
+    navigation_target::ToNav,
+    NavigationTarget, TryToNav,
+};
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct HighlightedRange {
     pub range: TextRange,
     // FIXME: This needs to be more precise. Reference category makes sense only
-    // for references, but we also have defs. And things like exit points are
-    // neither.
+    // for references, but we also have defs.
     pub category: Option<ReferenceCategory>,
+    pub kind: HighlightKind,
+}
+
+impl HighlightedRange {
+    /// The kind of construct this range was highlighted for, allowing editors to tell apart
+    /// highlights that share the same [`ReferenceCategory`].
+    pub fn kind(&self) -> HighlightKind {
+        self.kind
+    }
+}
+
+/// The kind of construct a [`HighlightedRange`] was produced for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// A reference to the item under the cursor, or another construct related to it.
+    Reference,
+    /// The definition of the item under the cursor.
+    Definition,
+    /// A captured local of a closure, or a use of it in the closure body.
+    Capture,
+    /// An exit point of a function, closure or block context.
+    Exit,
+    /// A `?` exit point that converts the error type before returning it.
+    ConvertingExit,
+    /// A break point of a loop or labeled block.
+    Break,
+    /// A yield point of an async context.
+    Yield,
+    /// The yield point under the cursor, set apart from the other yield points of its context.
+    PrimaryYield,
+    /// Code that is skipped by the early exit under the cursor.
+    Skipped,
+    /// A reference to a function in its own body.
+    Recursion,
+}
+
+/// The highlighted references to one of the definitions the token under the cursor refers to, as
+/// computed by [`crate::Analysis::highlight_related_by_def`].
+#[derive(Debug, Clone)]
+pub struct DefHighlights {
+    /// The navigation target of the definition, if it has one.
+    pub nav: Option<NavigationTarget>,
+    pub highlights: Vec<HighlightedRange>,
+}
+
+/// The kind of related items to compute with [`crate::Analysis::highlight_related_at`],
+/// regardless of the token under the cursor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HighlightRelatedKind {
+    /// References to the item under the cursor.
+    References,
+    /// Exit points of the innermost function, closure or block context.
+    ExitPoints,
+    /// Break points of the innermost loop or labeled block.
+    BreakPoints,
+    /// Yield points of the innermost async context or coroutine.
+    YieldPoints,
+    /// Captures of the innermost closure.
+    ClosureCaptures,
 }
 
 #[derive(Default, Clone)]
@@ -36,134 +109,1050 @@ pub struct HighlightRelatedConfig {
     pub break_points: bool,
     pub closure_captures: bool,
     pub yield_points: bool,
+    pub break_points_include_header: bool,
+    pub binding_origins: bool,
+    pub closure_captures_in_body: bool,
+    pub matching_brackets: bool,
+    pub max_references: Option<usize>,
+    pub exit_points_distinguish_try: bool,
+    pub yield_points_skip_ready: bool,
+    pub trait_impls: bool,
+    pub exclude_declaration: bool,
+    pub params_on_fn: bool,
+    pub unsafe_operations: bool,
+    pub return_type_usages: bool,
+    pub drop_points: bool,
+    pub exit_points_full_expr: bool,
+    pub yield_points_chain_only: bool,
+    pub yield_points_with_exits: bool,
+    pub match_arms: bool,
+    pub break_value_sources: bool,
+    pub exit_points_in_branch: bool,
+    pub references_in_strings: bool,
+    pub exit_points_show_skipped: bool,
+    pub references_adjacent_idents: bool,
+    pub exit_points_highlight_try_operand: bool,
+    pub discriminant_uses: bool,
+    pub references_all_macro_arms: bool,
+    pub exclude_builtin_types: bool,
+    pub highlight_recursion: bool,
 }
 
 // Feature: Highlight Related
 //
 // Highlights constructs related to the thing under the cursor:
 //
-// . if on an identifier, highlights all references to that identifier in the current file
-// .. additionally, if the identifier is a trait in a where clause, type parameter trait bound or use item, highlights all references to that trait's assoc items in the corresponding scope
+// . if on an identifier or a lifetime, highlights all references to it in the current file
+// .. alternatively, if right at the edge of a punctuation token between two identifiers and so configured, highlights the references of both
+// .. additionally, if on a placeholder of a format string naming an explicit argument, highlights the argument and the other placeholders using it
+// .. additionally, if on an explicit argument of a format string, highlights the placeholders using it
+// .. additionally, if so configured, highlights the intra-doc links to it in the current file
+// .. additionally, highlights the `*` of the glob imports in the current file that import it
+// .. additionally, if on a function and so configured, highlights the references to it in its own body as recursive calls
+// .. additionally, if on an enum variant and so configured, highlights its explicit discriminant and the `as` casts of its uses
+// .. additionally, if on a local initialized by a `loop` and so configured, highlights the `break`s producing its value
+// .. additionally, if on a local and so configured, highlights the end of the block the local goes out of scope at
+// .. additionally, if on the type of a tuple field, highlights all accesses of that field
+// .. if on a builtin type like `u32`, highlights all uses of that type unless configured otherwise
+// .. additionally, if on `Self` or the self type of an impl, highlights references to the self type and `self` within that impl, only those if on `Self`
+// .. additionally, if the identifier is a trait in a where clause, type parameter trait bound, `impl Trait` or `dyn Trait` type or use item, highlights all references to that trait's assoc items in the corresponding scope
+// .. additionally, if the identifier is the name of a trait declaration, highlights all references to the trait's assoc items in its default bodies
+// . if on the scrutinee of a `match` and so configured, additionally highlights the pattern of each arm
+// . if on a metavariable of a `macro_rules!` rule, highlights all its occurrences in that rule
+// . if on another identifier in a `macro_rules!` definition and so configured, highlights its occurrences in all arms of the definition
+// . if on the `mut` of a `let mut` binding, highlights the binding and all writes to the bound local
 // . if on an `async` or `await` token, highlights all yield points for that async context
-// . if on a `return` or `fn` keyword, `?` character or `->` return type arrow, highlights all exit points for that context
-// . if on a `break`, `loop`, `while` or `for` token, highlights all break points for that loop or block context
+// .. additionally, if on an `await` token and so configured, highlights all exit points for that async context
+// . if on a `yield` token, highlights all yield points for that coroutine
+// . if on a `return`, `fn` or `try` keyword, `?` character or `->` return type arrow, highlights all exit points for that context
+// .. additionally, in a test function, highlights the `assert!`, `assert_eq!` and `assert_ne!` calls as exit points
+// .. additionally, if on a `return` keyword or `?` character and so configured, highlights the code the exit skips
+// .. additionally, if on a `?` character and so configured, highlights the expression it is applied to
+// .. additionally, if on an `if` or `else` keyword or the `=>` of a match arm and so configured, highlights the exit points in that branch
+// .. alternatively, if on a `->` return type arrow, highlights all references to the returned type in the current file if so configured
+// . if on a `break`, `loop`, `while` or `for` token or a label, highlights all break points for that loop or block context
 // . if on a `move` or `|` token that belongs to a closure, highlights all captures of the closure.
+// . if on a brace, parenthesis or bracket, highlights the matching delimiter
+// . if on the `unsafe` keyword of a block or function, highlights all operations in it that require `unsafe`
 //
 // Note: `?`, `|` and `->` do not currently trigger this behavior in the VSCode editor.
 pub(crate) fn highlight_related(
     sema: &Semantics<'_, RootDatabase>,
     config: HighlightRelatedConfig,
-    pos @ FilePosition { offset, file_id }: FilePosition,
+    pos: FilePosition,
+) -> Option<Vec<HighlightedRange>> {
+    let mut res = Vec::new();
+    highlight_related_with(sema, config, pos, &mut |hl| {
+        res.push(hl);
+        ControlFlow::Continue(())
+    })?;
+    Some(res)
+}
+
+/// Like [`highlight_related`], but passes the highlighted ranges to `cb` one by one instead of
+/// collecting them, stopping as soon as `cb` returns [`ControlFlow::Break`]. Each text range is
+/// passed at most once. Returns `None` if [`highlight_related`] would.
+pub(crate) fn highlight_related_with(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos: FilePosition,
+    cb: &mut dyn FnMut(HighlightedRange) -> ControlFlow<()>,
+) -> Option<()> {
+    highlight_related_in(sema, config, pos, HighlightSink::new(cb))
+}
+
+/// Like [`highlight_related`], but on an `async`, `await` or `yield` token only highlights the yield
+/// points, the `async` keyword and, if so configured, the exit points within the selection of the
+/// user, if there is one.
+pub(crate) fn highlight_related_with_selection(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos: FilePosition,
+    selection: Option<TextRange>,
+) -> Option<Vec<HighlightedRange>> {
+    let mut res = Vec::new();
+    let mut cb = |hl| {
+        res.push(hl);
+        ControlFlow::Continue(())
+    };
+    let sink = HighlightSink { selection, ..HighlightSink::new(&mut cb) };
+    highlight_related_in(sema, config, pos, sink)?;
+    Some(res)
+}
+
+/// Like [`highlight_related`], but only searches for references within `range` and drops all
+/// highlights outside of it. The item under the cursor is resolved as usual.
+pub(crate) fn highlight_related_in_range(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos: FilePosition,
+    range: TextRange,
 ) -> Option<Vec<HighlightedRange>> {
+    let mut res = Vec::new();
+    let mut cb = |hl| {
+        res.push(hl);
+        ControlFlow::Continue(())
+    };
+    let sink = HighlightSink { range: Some(range), ..HighlightSink::new(&mut cb) };
+    highlight_related_in(sema, config, pos, sink)?;
+    Some(res)
+}
+
+fn highlight_related_in(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos @ FilePosition { file_id, .. }: FilePosition,
+    mut sink: HighlightSink<'_>,
+) -> Option<()> {
     let _p = profile::span("highlight_related");
-    let syntax = sema.parse(file_id).syntax().clone();
+    if config.references && config.references_adjacent_idents {
+        if let Some(idents) = adjacent_idents(sema, pos) {
+            for token in idents {
+                let pos = FilePosition { file_id, offset: token.text_range().start() };
+                highlight_references(sema, &config, token, pos, &mut sink);
+            }
+            return (!sink.seen.is_empty()).then_some(());
+        }
+    }
+    let token = pick_token(sema, pos)?;
+    if config.closure_captures && config.closure_captures_in_body {
+        if let Some(res) = highlight_closure_capture_in_body(sema, &token, file_id) {
+            sink.extend(res);
+            return Some(());
+        }
+    }
+    let match_arms = config.match_arms.then(|| highlight_match_arms(&token)).flatten();
+    let res = highlight_token(sema, &config, token, pos, &mut sink);
+    // the arm patterns come in addition to whatever the part of the scrutinee under the cursor
+    // highlights itself
+    match match_arms {
+        Some(arms) => {
+            sink.extend(arms);
+            Some(())
+        }
+        None => res,
+    }
+}
 
-    let token = pick_best_token(syntax.token_at_offset(offset), |kind| match kind {
-        T![?] => 4, // prefer `?` when the cursor is sandwiched like in `await$0?`
-        T![->] => 4,
-        kind if kind.is_keyword() => 3,
-        IDENT | INT_NUMBER => 2,
-        T![|] => 1,
-        _ => 0,
-    })?;
+/// Highlights the constructs related to `token` according to its kind.
+fn highlight_token(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    token: SyntaxToken,
+    pos @ FilePosition { file_id, .. }: FilePosition,
+    sink: &mut HighlightSink<'_>,
+) -> Option<()> {
     // most if not all of these should be re-implemented with information seeded from hir
-    match token.kind() {
+    let try_operand = (config.exit_points && config.exit_points_highlight_try_operand)
+        .then(|| highlight_try_operand(&token))
+        .flatten();
+    let mut res = match token.kind() {
+        T![?] | T![return]
+            if config.exit_points
+                && config.exit_points_show_skipped
+                && token.parent().map_or(false, |it| {
+                    ast::ReturnExpr::can_cast(it.kind()) || ast::TryExpr::can_cast(it.kind())
+                }) =>
+        {
+            let skipped = highlight_skipped_by_exit(&token)?;
+            let mut res = highlight_exit_points(sema, config, token)?;
+            res.extend(skipped);
+            Some(res)
+        }
         T![?] if config.exit_points && token.parent().and_then(ast::TryExpr::cast).is_some() => {
-            highlight_exit_points(sema, token)
+            highlight_exit_points(sema, config, token)
+        }
+        T![fn] if config.exit_points && config.params_on_fn => {
+            let mut res = highlight_exit_points(sema, config, token.clone()).unwrap_or_default();
+            for param in highlight_fn_params(&token).into_iter().flatten() {
+                if !res.contains(&param) {
+                    res.push(param);
+                }
+            }
+            Some(res)
+        }
+        T![->] if config.return_type_usages => match return_type_name_ref(&token) {
+            Some(name_ref) => {
+                return highlight_return_type_usages(sema, config, name_ref, file_id, sink);
+            }
+            // there is no single type to highlight the usages of
+            None if config.exit_points => highlight_exit_points(sema, config, token),
+            None => None,
+        },
+        T![fn] | T![return] | T![->] if config.exit_points => {
+            highlight_exit_points(sema, config, token)
+        }
+        T![try]
+            if config.exit_points && token.parent().and_then(ast::BlockExpr::cast).is_some() =>
+        {
+            highlight_exit_points(sema, config, token)
+        }
+        T![if] | T![else] | T![=>] if config.exit_points && config.exit_points_in_branch => {
+            highlight_branch_exit_points(sema, config, token)
+        }
+        T![await] if config.yield_points && config.yield_points_with_exits => {
+            let mut res = highlight_yield_points(sema, config, token.clone()).unwrap_or_default();
+            res.extend(highlight_exit_points(sema, config, token).into_iter().flatten());
+            Some(retain_in_selection(res, sink.selection))
+        }
+        T![await] | T![async] | T![yield] if config.yield_points => {
+            highlight_yield_points(sema, config, token)
+                .map(|res| retain_in_selection(res, sink.selection))
         }
-        T![fn] | T![return] | T![->] if config.exit_points => highlight_exit_points(sema, token),
-        T![await] | T![async] if config.yield_points => highlight_yield_points(token),
         T![for] if config.break_points && token.parent().and_then(ast::ForExpr::cast).is_some() => {
-            highlight_break_points(token)
+            highlight_break_points(sema, config, token)
         }
         T![break] | T![loop] | T![while] | T![continue] if config.break_points => {
-            highlight_break_points(token)
+            highlight_break_points(sema, config, token)
+        }
+        T![lifetime_ident]
+            if config.break_points
+                && token
+                    .parent()
+                    .and_then(|it| it.parent())
+                    .and_then(ast::Label::cast)
+                    .is_some() =>
+        {
+            highlight_break_points(sema, config, token)
+        }
+        T!['{'] | T!['}'] | T!['('] | T![')'] | T!['['] | T![']'] if config.matching_brackets => {
+            highlight_matching_brackets(token)
+        }
+        T![|] | T![move] if config.closure_captures => {
+            let closure = closure_of_token(&token)?;
+            highlight_closure_captures(sema, closure, file_id)
+        }
+        T![mut] if config.references => match highlight_local_writes(sema, &token, file_id) {
+            Some(res) => Some(res),
+            None => return highlight_references(sema, config, token, pos, sink),
+        },
+        T![unsafe] if config.unsafe_operations => highlight_unsafe_operations(sema, token),
+        _ if config.references => match highlight_macro_metavar(&token).or_else(|| {
+            config.references_all_macro_arms.then(|| highlight_macro_arm_idents(&token)).flatten()
+        }) {
+            Some(res) => Some(res),
+            None => return highlight_references(sema, config, token, pos, sink),
+        },
+        _ => None,
+    }?;
+    res.extend(try_operand);
+    sink.extend(res);
+    Some(())
+}
+
+/// Passes highlighted ranges on to a callback, skipping the ones whose range it has already passed
+/// on and remembering whether the callback asked to stop. Whichever highlight of a range comes
+/// first wins, so producers pass on the ones with a reference category first.
+struct HighlightSink<'a> {
+    cb: &'a mut dyn FnMut(HighlightedRange) -> ControlFlow<()>,
+    seen: FxHashSet<TextRange>,
+    stopped: bool,
+    /// The range to restrict the highlights and the reference search to, if any.
+    range: Option<TextRange>,
+    /// The selection of the user, if any.
+    selection: Option<TextRange>,
+}
+
+impl<'a> HighlightSink<'a> {
+    fn new(cb: &'a mut dyn FnMut(HighlightedRange) -> ControlFlow<()>) -> Self {
+        HighlightSink {
+            cb,
+            seen: FxHashSet::default(),
+            stopped: false,
+            range: None,
+            selection: None,
+        }
+    }
+
+    fn push(&mut self, hl: HighlightedRange) {
+        if self.range.map_or(false, |range| !range.contains_range(hl.range)) {
+            return;
+        }
+        if !self.stopped && self.seen.insert(hl.range) {
+            self.stopped = (self.cb)(hl).is_break();
+        }
+    }
+
+    fn extend(&mut self, hls: impl IntoIterator<Item = HighlightedRange>) {
+        for hl in hls {
+            if self.stopped {
+                break;
+            }
+            self.push(hl);
+        }
+    }
+}
+
+/// Like [`highlight_related`], but keys the ranges by the file they are in, additionally including
+/// the names of the referenced definitions that live in other files.
+pub(crate) fn highlight_related_in_files(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos @ FilePosition { file_id, .. }: FilePosition,
+) -> Option<Vec<(FileId, HighlightedRange)>> {
+    let (references, exclude_declaration) = (config.references, config.exclude_declaration);
+    let mut res: Vec<_> =
+        highlight_related(sema, config, pos)?.into_iter().map(|hl| (file_id, hl)).collect();
+    if references && !exclude_declaration {
+        let token = pick_token(sema, pos)?;
+        for def in find_defs(sema, token) {
+            res.extend(
+                def_name_file_ranges(sema, def)
+                    .into_iter()
+                    .filter(|range| range.file_id != file_id)
+                    .map(|FileRange { file_id, range }| {
+                        let kind = HighlightKind::Definition;
+                        (file_id, HighlightedRange { range, category: None, kind })
+                    }),
+            );
+        }
+    }
+    Some(res)
+}
+
+/// Highlights the references of the token at `pos` like [`highlight_related`], but separately for
+/// each definition the token refers to. A token can refer to several definitions when a macro
+/// pastes it into several places.
+pub(crate) fn highlight_related_by_def(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos @ FilePosition { file_id, .. }: FilePosition,
+) -> Option<Vec<DefHighlights>> {
+    let _p = profile::span("highlight_related_by_def");
+    let token = pick_token(sema, pos)?;
+    let res = find_defs(sema, token.clone())
+        .into_iter()
+        .map(|def| {
+            let mut highlights = Vec::new();
+            let mut cb = |hl| {
+                highlights.push(hl);
+                ControlFlow::Continue(())
+            };
+            highlight_def_references(
+                sema,
+                &config,
+                token.clone(),
+                iter::once(def).collect(),
+                None,
+                file_id,
+                &mut HighlightSink::new(&mut cb),
+            );
+            let nav = def.try_to_nav(sema.db).map(|it| it.call_site);
+            DefHighlights { nav, highlights }
+        })
+        .collect::<Vec<_>>();
+    (!res.is_empty()).then_some(res)
+}
+
+/// Computes the related items of the given `kind` for the token at `pos`, even if the token
+/// itself would not trigger them in [`highlight_related`]. The enable flags of `config` are
+/// ignored, all other options apply as usual.
+pub(crate) fn highlight_related_at(
+    sema: &Semantics<'_, RootDatabase>,
+    config: HighlightRelatedConfig,
+    pos: FilePosition,
+    kind: HighlightRelatedKind,
+) -> Option<Vec<HighlightedRange>> {
+    let _p = profile::span("highlight_related_at");
+    let token = pick_token(sema, pos)?;
+    match kind {
+        HighlightRelatedKind::References => {
+            let mut res = Vec::new();
+            let mut cb = |hl| {
+                res.push(hl);
+                ControlFlow::Continue(())
+            };
+            highlight_references(sema, &config, token, pos, &mut HighlightSink::new(&mut cb))?;
+            Some(res)
+        }
+        HighlightRelatedKind::ExitPoints => highlight_exit_points(sema, &config, token),
+        HighlightRelatedKind::BreakPoints => highlight_break_points(sema, &config, token),
+        HighlightRelatedKind::YieldPoints => highlight_yield_points(sema, &config, token),
+        HighlightRelatedKind::ClosureCaptures => {
+            let closure = token.parent_ancestors().find_map(ast::ClosureExpr::cast)?;
+            highlight_closure_captures(sema, closure, pos.file_id)
+        }
+    }
+}
+
+fn pick_token(
+    sema: &Semantics<'_, RootDatabase>,
+    FilePosition { offset, file_id }: FilePosition,
+) -> Option<SyntaxToken> {
+    let syntax = sema.parse(file_id).syntax().clone();
+    // punctuation like the `.` in `x.await$0.field` never wins, so the `await` is picked there and
+    // the field in `x.await.$0field`; on ties the token starting at the cursor wins
+    pick_best_token(syntax.token_at_offset(offset), |kind| match kind {
+        T![?] => 4, // prefer `?` when the cursor is sandwiched like in `await$0?`
+        T![->] => 4,
+        kind if kind.is_keyword() => 3,
+        IDENT | INT_NUMBER | LIFETIME_IDENT => 2,
+        T![|] | T!['{'] | T!['}'] | T!['('] | T![')'] | T!['['] | T![']'] => 1,
+        _ => 0,
+    })
+}
+
+/// The identifiers on both sides of the punctuation token the cursor is at the edge of, like `foo`
+/// and `bar` in `foo$0.bar` and `foo.$0bar`.
+fn adjacent_idents(
+    sema: &Semantics<'_, RootDatabase>,
+    FilePosition { offset, file_id }: FilePosition,
+) -> Option<[SyntaxToken; 2]> {
+    let syntax = sema.parse(file_id).syntax().clone();
+    let TokenAtOffset::Between(left, right) = syntax.token_at_offset(offset) else {
+        return None;
+    };
+    let [left, right] = match (left.kind() == IDENT, right.kind() == IDENT) {
+        (true, false) if right.kind().is_punct() => [left, right.next_token()?],
+        (false, true) if left.kind().is_punct() => [left.prev_token()?, right],
+        _ => return None,
+    };
+    (left.kind() == IDENT && right.kind() == IDENT).then_some([left, right])
+}
+
+/// Highlights the delimiter under the cursor together with its partner. Both delimiters are always
+/// direct children of the same node, including the delimiters of macro calls which belong to the
+/// macro's token tree.
+fn highlight_matching_brackets(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let (partner, direction) = match token.kind() {
+        T!['{'] => (T!['}'], Direction::Next),
+        T!['('] => (T![')'], Direction::Next),
+        T!['['] => (T![']'], Direction::Next),
+        T!['}'] => (T!['{'], Direction::Prev),
+        T![')'] => (T!['('], Direction::Prev),
+        T![']'] => (T!['['], Direction::Prev),
+        _ => return None,
+    };
+    let partner = token
+        .siblings_with_tokens(direction)
+        .skip(1)
+        .filter_map(|it| it.into_token())
+        .find(|it| it.kind() == partner)?;
+    Some(
+        [token, partner]
+            .into_iter()
+            .map(|it| HighlightedRange {
+                range: it.text_range(),
+                category: None,
+                kind: HighlightKind::Reference,
+            })
+            .collect(),
+    )
+}
+
+/// Finds the closure the `|` or `move` token belongs to, that is the closure whose parameter list
+/// or `move` keyword the token is, as opposed to a closure the token is merely nested in.
+fn closure_of_token(token: &SyntaxToken) -> Option<ast::ClosureExpr> {
+    match token.kind() {
+        T![move] => token.parent().and_then(ast::ClosureExpr::cast),
+        T![|] => {
+            let param_list = token.parent().and_then(ast::ParamList::cast)?;
+            param_list.syntax().parent().and_then(ast::ClosureExpr::cast)
         }
-        T![|] if config.closure_captures => highlight_closure_captures(sema, token, file_id),
-        T![move] if config.closure_captures => highlight_closure_captures(sema, token, file_id),
-        _ if config.references => highlight_references(sema, token, pos),
         _ => None,
     }
 }
 
 fn highlight_closure_captures(
     sema: &Semantics<'_, RootDatabase>,
-    token: SyntaxToken,
+    closure: ast::ClosureExpr,
     file_id: FileId,
 ) -> Option<Vec<HighlightedRange>> {
-    let closure = token.parent_ancestors().take(2).find_map(ast::ClosureExpr::cast)?;
+    let _p = profile::span("highlight_closure_captures");
     let search_range = closure.body()?.syntax().text_range();
     let ty = &sema.type_of_expr(&closure.into())?.original;
     let c = ty.as_closure()?;
     Some(
         c.captured_items(sema.db)
             .into_iter()
-            .map(|capture| capture.local())
-            .flat_map(|local| {
-                let usages = Definition::Local(local)
-                    .usages(sema)
-                    .in_scope(&SearchScope::file_range(FileRange { file_id, range: search_range }))
-                    .include_self_refs()
-                    .all()
-                    .references
-                    .remove(&file_id)
-                    .into_iter()
-                    .flatten()
-                    .map(|FileReference { category, range, .. }| HighlightedRange {
-                        range,
-                        category,
-                    });
-                let category = local.is_mut(sema.db).then_some(ReferenceCategory::Write);
-                local
-                    .sources(sema.db)
-                    .into_iter()
-                    .flat_map(|x| x.to_nav(sema.db))
-                    .filter(|decl| decl.file_id == file_id)
-                    .filter_map(|decl| decl.focus_range)
-                    .map(move |range| HighlightedRange { range, category })
-                    .chain(usages)
-            })
+            .flat_map(|capture| highlight_capture(sema, &capture, file_id, search_range))
             .collect(),
     )
 }
 
+/// Returns the name of the type the return type the `->` token belongs to consists of, if it is a
+/// path type.
+fn return_type_name_ref(token: &SyntaxToken) -> Option<ast::NameRef> {
+    match token.parent().and_then(ast::RetType::cast)?.ty()? {
+        ast::Type::PathType(it) => it.path()?.segment()?.name_ref(),
+        _ => None,
+    }
+}
+
+/// Highlights the references to the type named by the return type of a function or closure.
+fn highlight_return_type_usages(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    name_ref: ast::NameRef,
+    file_id: FileId,
+    sink: &mut HighlightSink<'_>,
+) -> Option<()> {
+    let token = name_ref.syntax().first_token()?;
+    let offset = token.text_range().start();
+    highlight_references(sema, config, token, FilePosition { file_id, offset }, sink)
+}
+
+/// Highlights the identifiers with the same text as the one under the cursor in all arms of the
+/// `macro_rules!` definition it belongs to, leaving out metavariables.
+fn highlight_macro_arm_idents(token: &SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let is_metavar =
+        |it: &SyntaxToken| it.prev_sibling_or_token().map_or(false, |it| it.kind() == T![$]);
+    if token.kind() != IDENT || is_metavar(token) {
+        return None;
+    }
+    let rules = token.parent_ancestors().find_map(ast::MacroRules::cast)?.token_tree()?;
+    let highlights = rules
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|it| it.into_token())
+        .filter(|it| it.kind() == IDENT && it.text() == token.text() && !is_metavar(it))
+        .map(|it| HighlightedRange {
+            range: it.text_range(),
+            category: None,
+            kind: HighlightKind::Reference,
+        })
+        .collect();
+    Some(highlights)
+}
+
+/// Highlights the `*` of the glob imports in the file that import one of `defs`, as they do not name
+/// them and the reference search can't find them. Only the items declared in the globbed module
+/// itself are considered, not the ones it re-exports.
+fn highlight_glob_imports(
+    sema: &Semantics<'_, RootDatabase>,
+    defs: &FxHashSet<Definition>,
+    file_id: FileId,
+) -> Vec<HighlightedRange> {
+    let has_items = defs.iter().any(|def| {
+        matches!(
+            def,
+            Definition::Module(_)
+                | Definition::Function(_)
+                | Definition::Adt(_)
+                | Definition::Variant(_)
+                | Definition::Const(_)
+                | Definition::Static(_)
+                | Definition::Trait(_)
+                | Definition::TraitAlias(_)
+                | Definition::TypeAlias(_)
+                | Definition::Macro(_)
+        )
+    });
+    if !has_items {
+        return Vec::new();
+    }
+    let root = sema.parse(file_id);
+    root.syntax()
+        .descendants()
+        .filter_map(ast::UseTree::cast)
+        .filter_map(|use_tree| {
+            let star = use_tree.star_token()?;
+            let module = sema.scope(use_tree.syntax())?.module();
+            let imports_def = match sema.resolve_path(&use_tree.path()?)? {
+                PathResolution::Def(ModuleDef::Module(it)) => defs.iter().any(|def| {
+                    !matches!(def, Definition::Variant(_))
+                        && def.module(sema.db) == Some(it)
+                        && def
+                            .visibility(sema.db)
+                            .map_or(false, |vis| vis.is_visible_from(sema.db, module.into()))
+                }),
+                PathResolution::Def(ModuleDef::Adt(hir::Adt::Enum(it))) => defs.iter().any(
+                    |def| matches!(def, Definition::Variant(v) if v.parent_enum(sema.db) == it),
+                ),
+                _ => false,
+            };
+            imports_def.then(|| HighlightedRange {
+                range: star.text_range(),
+                category: Some(ReferenceCategory::Import),
+                kind: HighlightKind::Reference,
+            })
+        })
+        .collect()
+}
+
+/// Highlights the explicit discriminant of an enum variant and the `as` casts of the given uses of
+/// it, which read its discriminant.
+fn highlight_discriminant_uses(
+    sema: &Semantics<'_, RootDatabase>,
+    variant: hir::Variant,
+    uses: &[TextRange],
+    file_id: FileId,
+) -> Vec<HighlightedRange> {
+    let discriminant = variant
+        .source(sema.db)
+        .filter(|source| source.file_id == file_id.into())
+        .and_then(|source| source.value.expr())
+        .map(|expr| HighlightedRange {
+            range: expr.syntax().text_range(),
+            category: None,
+            kind: HighlightKind::Reference,
+        });
+    let root = sema.parse(file_id);
+    let casts = uses.iter().filter_map(|&range| {
+        let name_ref = find_node_at_range::<ast::NameRef>(root.syntax(), range)?;
+        let path_expr = full_path_of_name_ref(&name_ref)?.syntax().parent()?;
+        if !ast::PathExpr::can_cast(path_expr.kind()) {
+            return None;
+        }
+        let cast = path_expr.parent().and_then(ast::CastExpr::cast)?;
+        Some(HighlightedRange {
+            range: cast.syntax().text_range(),
+            category: Some(ReferenceCategory::Read),
+            kind: HighlightKind::Reference,
+        })
+    });
+    discriminant.into_iter().chain(casts).collect()
+}
+
+/// Highlights the occurrences of the `macro_rules!` metavariable under the cursor in the rule it
+/// belongs to, including its binder in the matcher.
+fn highlight_macro_metavar(token: &SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let (dollar, name) = match token.kind() {
+        T![$] => (token.clone(), token.next_sibling_or_token()?.into_token()?),
+        IDENT => (token.prev_sibling_or_token()?.into_token()?, token.clone()),
+        _ => return None,
+    };
+    if dollar.kind() != T![$] || name.kind() != IDENT {
+        return None;
+    }
+    let rules = token.parent_ancestors().find_map(ast::MacroRules::cast)?.token_tree()?;
+    // the rules alternate between matchers and transcribers
+    let rule_parts: Vec<_> = rules.syntax().children().filter_map(ast::TokenTree::cast).collect();
+    let idx = rule_parts
+        .iter()
+        .position(|it| it.syntax().text_range().contains_range(token.text_range()))?;
+    let matcher = idx - idx % 2;
+    let highlights = rule_parts
+        .iter()
+        .skip(matcher)
+        .take(2)
+        .flat_map(|it| it.syntax().descendants_with_tokens())
+        .filter_map(|it| it.into_token())
+        .filter(|it| it.kind() == T![$])
+        .filter_map(|it| {
+            let next = it.next_sibling_or_token()?.into_token()?;
+            (next.kind() == IDENT && next.text() == name.text())
+                .then(|| it.text_range().cover(next.text_range()))
+        })
+        .map(|range| HighlightedRange { range, category: None, kind: HighlightKind::Reference })
+        .collect();
+    Some(highlights)
+}
+
+/// Highlights the writes to the local bound by the `let mut` binding the `mut` token belongs to,
+/// together with the binding.
+fn highlight_local_writes(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+    file_id: FileId,
+) -> Option<Vec<HighlightedRange>> {
+    let ident_pat = token.parent().and_then(ast::IdentPat::cast)?;
+    // the binding has to be part of the pattern of the `let`, not e.g. a parameter of a closure in
+    // its initializer
+    let let_pat = ident_pat.syntax().ancestors().find_map(ast::LetStmt::cast)?.pat()?;
+    if !let_pat.syntax().text_range().contains_range(ident_pat.syntax().text_range()) {
+        return None;
+    }
+    let local = sema.to_def(&ident_pat)?;
+    // the binding itself, so that there is something to highlight even if it is never written to
+    let mut res: Vec<_> = ident_pat
+        .name()
+        .map(|name| HighlightedRange {
+            range: name.syntax().text_range(),
+            category: Some(ReferenceCategory::Write),
+            kind: HighlightKind::Definition,
+        })
+        .into_iter()
+        .collect();
+    Definition::Local(local).usages(sema).in_scope(&SearchScope::single_file(file_id)).search(
+        &mut |reference_file_id, FileReference { category, range, .. }| {
+            if reference_file_id == file_id && category == Some(ReferenceCategory::Write) {
+                res.push(HighlightedRange { range, category, kind: HighlightKind::Reference });
+            }
+            false
+        },
+    );
+    Some(res)
+}
+
+/// Highlights the `unsafe` keyword of an unsafe block or function together with the operations in
+/// its body that require it. Nested unsafe blocks and items are skipped, as they have their own
+/// `unsafe` keyword or context.
+fn highlight_unsafe_operations(
+    sema: &Semantics<'_, RootDatabase>,
+    token: SyntaxToken,
+) -> Option<Vec<HighlightedRange>> {
+    let body = match_ast! {
+        match (token.parent()?) {
+            ast::BlockExpr(it) => it.syntax().clone(),
+            ast::Fn(it) => it.body()?.syntax().clone(),
+            _ => return None,
+        }
+    };
+    let mut highlights = vec![HighlightedRange {
+        range: token.text_range(),
+        category: None,
+        kind: HighlightKind::Reference,
+    }];
+    let mut preorder = body.preorder();
+    while let Some(event) = preorder.next() {
+        let node = match event {
+            WalkEvent::Enter(node) => node,
+            WalkEvent::Leave(_) => continue,
+        };
+        if node != body {
+            let is_unsafe_block =
+                ast::BlockExpr::cast(node.clone()).map_or(false, |it| it.unsafe_token().is_some());
+            if is_unsafe_block || ast::Item::can_cast(node.kind()) {
+                preorder.skip_subtree();
+                continue;
+            }
+        }
+        if let Some(range) = unsafe_operation_range(sema, &node) {
+            highlights.push(HighlightedRange {
+                range,
+                category: None,
+                kind: HighlightKind::Reference,
+            });
+        }
+    }
+    Some(highlights)
+}
+
+/// The range to highlight for `node` if it is an operation that requires `unsafe`.
+fn unsafe_operation_range(
+    sema: &Semantics<'_, RootDatabase>,
+    node: &SyntaxNode,
+) -> Option<TextRange> {
+    let db = sema.db;
+    match_ast! {
+        match node {
+            ast::PrefixExpr(it) => {
+                let is_raw_ptr_deref = it.op_kind()? == ast::UnaryOp::Deref
+                    && sema.type_of_expr(&it.expr()?)?.original.is_raw_ptr();
+                is_raw_ptr_deref.then(|| it.op_token()).flatten().map(|it| it.text_range())
+            },
+            ast::MethodCallExpr(it) => {
+                let is_unsafe = sema.is_unsafe_method_call(&it)
+                    || sema.resolve_method_call(&it)?.is_unsafe_to_call(db);
+                is_unsafe.then(|| it.name_ref()).flatten().map(|it| it.syntax().text_range())
+            },
+            ast::CallExpr(it) => {
+                let callee = it.expr()?;
+                let callable = sema.type_of_expr(&callee)?.original.as_callable(db)?;
+                match callable.kind() {
+                    hir::CallableKind::Function(func) if func.is_unsafe_to_call(db) => {
+                        Some(callee.syntax().text_range())
+                    }
+                    _ => None,
+                }
+            },
+            ast::FieldExpr(it) => match sema.resolve_field(&it)? {
+                Either::Left(field) if matches!(field.parent_def(db), hir::VariantDef::Union(_)) => {
+                    Some(it.name_ref()?.syntax().text_range())
+                }
+                _ => None,
+            },
+            ast::PathExpr(it) => {
+                let path = it.path()?;
+                match sema.resolve_path(&path)? {
+                    PathResolution::Def(ModuleDef::Static(it)) if it.is_mut(db) => {
+                        Some(path.syntax().text_range())
+                    }
+                    _ => None,
+                }
+            },
+            ast::RefExpr(it) => sema.is_unsafe_ref_expr(&it).then(|| it.syntax().text_range()),
+            _ => None,
+        }
+    }
+}
+
+/// Highlights the pattern of each arm of the match whose scrutinee the cursor is on. The
+/// alternatives of or-patterns are highlighted separately, guards are left out.
+fn highlight_match_arms(token: &SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let match_expr = token.parent_ancestors().find_map(ast::MatchExpr::cast)?;
+    if !match_expr.expr()?.syntax().text_range().contains_range(token.text_range()) {
+        return None;
+    }
+    let highlights = match_expr
+        .match_arm_list()?
+        .arms()
+        .filter_map(|arm| arm.pat())
+        .flat_map(|pat| match pat {
+            ast::Pat::OrPat(or_pat) => Either::Left(or_pat.pats()),
+            pat => Either::Right(iter::once(pat)),
+        })
+        .map(|pat| HighlightedRange {
+            range: pat.syntax().text_range(),
+            category: None,
+            kind: HighlightKind::Reference,
+        })
+        .collect();
+    Some(highlights)
+}
+
+/// Highlights the captured variable under the cursor if it is used inside the body of a closure
+/// capturing it, restricting the highlighted uses to that closure.
+fn highlight_closure_capture_in_body(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+    file_id: FileId,
+) -> Option<Vec<HighlightedRange>> {
+    let name_ref = token.parent().and_then(ast::NameRef::cast)?;
+    let Some(NameRefClass::Definition(Definition::Local(local))) =
+        NameRefClass::classify(sema, &name_ref)
+    else {
+        return None;
+    };
+    name_ref.syntax().ancestors().filter_map(ast::ClosureExpr::cast).find_map(|closure| {
+        let search_range = closure.body()?.syntax().text_range();
+        let ty = sema.type_of_expr(&closure.into())?.original;
+        let capture = ty
+            .as_closure()?
+            .captured_items(sema.db)
+            .into_iter()
+            .find(|capture| capture.local() == local)?;
+        Some(highlight_capture(sema, &capture, file_id, search_range))
+    })
+}
+
+fn highlight_capture(
+    sema: &Semantics<'_, RootDatabase>,
+    capture: &ClosureCapture,
+    file_id: FileId,
+    search_range: TextRange,
+) -> Vec<HighlightedRange> {
+    let local = capture.local();
+    // the declaration and the uses all get the category of the capture mode, so that they can be
+    // told apart from the ones of captures with other modes
+    let category = match capture.kind() {
+        CaptureKind::SharedRef => None,
+        CaptureKind::UniqueSharedRef | CaptureKind::MutableRef => Some(ReferenceCategory::Write),
+        // capturing a `Copy` value by value copies it, leaving the local intact
+        CaptureKind::Move if local.ty(sema.db).is_copy(sema.db) => None,
+        CaptureKind::Move => Some(ReferenceCategory::Move),
+    };
+    let usages = Definition::Local(local)
+        .usages(sema)
+        .in_scope(&SearchScope::file_range(FileRange { file_id, range: search_range }))
+        .include_self_refs()
+        .all()
+        .references
+        .remove(&file_id)
+        .into_iter()
+        .flatten()
+        .map(|FileReference { range, .. }| HighlightedRange {
+            range,
+            category,
+            kind: HighlightKind::Capture,
+        });
+    local
+        .sources(sema.db)
+        .into_iter()
+        .flat_map(|x| x.to_nav(sema.db))
+        .filter(|decl| decl.file_id == file_id)
+        .filter_map(|decl| decl.focus_range)
+        .map(|range| HighlightedRange { range, category, kind: HighlightKind::Capture })
+        .chain(usages)
+        .collect()
+}
+
 fn highlight_references(
     sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
     token: SyntaxToken,
     FilePosition { file_id, offset }: FilePosition,
-) -> Option<Vec<HighlightedRange>> {
+    sink: &mut HighlightSink<'_>,
+) -> Option<()> {
+    let _p = profile::span("highlight_references");
+    if let Some(res) = highlight_format_args_placeholder(sema, &token, offset, file_id) {
+        sink.extend(res);
+        return Some(());
+    }
     let defs = if let Some((range, resolution)) =
         sema.check_for_format_args_template(token.clone(), offset)
     {
         match resolution.map(Definition::from) {
             Some(def) => iter::once(def).collect(),
-            None => return Some(vec![HighlightedRange { range, category: None }]),
+            None => {
+                sink.push(HighlightedRange {
+                    range,
+                    category: None,
+                    kind: HighlightKind::Reference,
+                });
+                return Some(());
+            }
         }
     } else {
-        find_defs(sema, token.clone())
+        let mut defs = find_defs(sema, token.clone());
+        // the type of a tuple field also stands in for the field, which has no name
+        defs.extend(tuple_field_of_type(sema, &token).map(Definition::Field));
+        defs
     };
-    let usages = defs
-        .iter()
-        .filter_map(|&d| {
-            d.usages(sema)
-                .in_scope(&SearchScope::single_file(file_id))
-                .include_self_refs()
-                .all()
-                .references
-                .remove(&file_id)
-        })
-        .flatten()
-        .map(|FileReference { category, range, .. }| HighlightedRange { range, category });
-    let mut res = FxHashSet::default();
+    let alias = find_use_alias(sema, &token, &defs, file_id);
+    highlight_def_references(sema, config, token, defs, alias, file_id, sink)
+}
+
+/// Highlights the references to `defs`, the definitions the token under the cursor refers to. With
+/// an `alias`, only the alias and the uses through it are highlighted.
+fn highlight_def_references(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    token: SyntaxToken,
+    mut defs: FxHashSet<Definition>,
+    alias: Option<ast::Name>,
+    file_id: FileId,
+    sink: &mut HighlightSink<'_>,
+) -> Option<()> {
+    if config.exclude_builtin_types {
+        defs.retain(|def| !matches!(def, Definition::BuiltinType(_)));
+    }
+    let mut search_scope = match sink.range {
+        Some(range) => SearchScope::file_range(FileRange { file_id, range }),
+        None => SearchScope::single_file(file_id),
+    };
+    if let Some(impl_) = self_type_impl(sema, &defs, &token) {
+        if defs.iter().any(|def| matches!(def, Definition::SelfType(_))) {
+            // `Self` only refers to the self type within the impl block
+            let range = impl_.syntax().text_range();
+            let range =
+                sink.range.map_or(Some(range), |it| it.intersect(range)).unwrap_or_default();
+            search_scope = SearchScope::file_range(FileRange { file_id, range });
+            defs = self_type_defs(sema, &impl_);
+        } else {
+            // the name of the self type is still referred to outside of the impl block
+            defs.extend(self_type_defs(sema, &impl_));
+        }
+    }
+    if let Some(alias) = &alias {
+        // the alias is only in scope in the item list of the `use` declaring it
+        let range = alias.syntax().ancestors().find_map(ast::Use::cast)?.syntax().parent()?;
+        let range = range.text_range();
+        let range = sink.range.map_or(Some(range), |it| it.intersect(range)).unwrap_or_default();
+        search_scope = SearchScope::file_range(FileRange { file_id, range });
+    }
+    let limit_reached = |count: usize| config.max_references.map_or(false, |max| count >= max);
+    let mut usages = 0;
+    // the ranges of the uses of enum variants, for finding the casts of them to integers
+    let mut variant_uses = Vec::new();
+    let source_file = sema.parse(file_id);
     for &def in &defs {
-        // highlight trait usages
-        if let Definition::Trait(t) = def {
-            let trait_item_use_scope = (|| {
-                let name_ref = token.parent().and_then(ast::NameRef::cast)?;
-                let path = full_path_of_name_ref(&name_ref)?;
-                let parent = path.syntax().parent()?;
-                match_ast! {
-                    match parent {
+        if limit_reached(usages) || sink.stopped {
+            break;
+        }
+        sema.db.unwind_if_cancelled();
+        // the body of the function, in which its references are recursive calls
+        let recursion_range = match def {
+            Definition::Function(func) if config.highlight_recursion => func
+                .source(sema.db)
+                .filter(|source| source.file_id == file_id.into())
+                .and_then(|source| source.value.body())
+                .map(|body| body.syntax().text_range()),
+            _ => None,
+        };
+        let find_usages = def.usages(sema).in_scope(&search_scope);
+        let find_usages = match &alias {
+            Some(alias) => find_usages.with_alias(alias.text().as_str().into()),
+            None => find_usages.include_self_refs(),
+        };
+        find_usages.search(&mut |reference_file_id, FileReference { category, range, .. }| {
+            if reference_file_id == file_id
+                && !in_cfg_disabled_item(sema, source_file.syntax(), range)
+            {
+                usages += 1;
+                if let Definition::Variant(variant) = def {
+                    variant_uses.push((variant, range));
+                }
+                let kind = if recursion_range.map_or(false, |it| it.contains_range(range)) {
+                    HighlightKind::Recursion
+                } else {
+                    HighlightKind::Reference
+                };
+                sink.push(HighlightedRange { range, category, kind });
+            }
+            limit_reached(usages) || sink.stopped
+        });
+    }
+    if let Some(alias) = alias {
+        if !config.exclude_declaration && !sink.stopped {
+            sink.push(HighlightedRange {
+                range: alias.syntax().text_range(),
+                category: None,
+                kind: HighlightKind::Definition,
+            });
+        }
+        return Some(());
+    }
+    if config.references_in_strings && !sink.stopped {
+        sink.extend(highlight_doc_link_references(sema, &defs, file_id));
+    }
+    if !sink.stopped {
+        sink.extend(highlight_glob_imports(sema, &defs, file_id));
+    }
+    // the definitions come after the usages, so that a definition which is also a reference to
+    // another definition, like a field shorthand pattern, keeps the category of the reference
+    for &def in &defs {
+        if sink.stopped {
+            break;
+        }
+        // highlight trait usages
+        if let Definition::Trait(t) = def {
+            let on_trait_name = token
+                .parent()
+                .and_then(ast::Name::cast)
+                .and_then(|it| it.syntax().parent())
+                .and_then(ast::Trait::cast);
+            let trait_item_use_scope = (|| {
+                // on the declaration, the uses are the ones in the default bodies of the items
+                if let Some(trait_) = &on_trait_name {
+                    return trait_.assoc_item_list().map(|it| it.syntax().clone());
+                }
+                let name_ref = token.parent().and_then(ast::NameRef::cast)?;
+                let path = full_path_of_name_ref(&name_ref)?;
+                let parent = path.syntax().parent()?;
+                match_ast! {
+                    match parent {
                         ast::UseTree(it) => it.syntax().ancestors().find(|it| {
                             ast::SourceFile::can_cast(it.kind()) || ast::Module::can_cast(it.kind())
                         }),
@@ -174,7 +1163,12 @@ fn highlight_references(
                             .and_then(ast::TypeBoundList::cast)?
                             .syntax()
                             .parent()
-                            .filter(|it| ast::WhereClause::can_cast(it.kind()) || ast::TypeParam::can_cast(it.kind()))?
+                            .filter(|it| {
+                                ast::WherePred::can_cast(it.kind())
+                                    || ast::TypeParam::can_cast(it.kind())
+                                    || ast::ImplTraitType::can_cast(it.kind())
+                                    || ast::DynTraitType::can_cast(it.kind())
+                            })?
                             .ancestors()
                             .find(|it| {
                                 ast::Item::can_cast(it.kind())
@@ -184,7 +1178,7 @@ fn highlight_references(
                 }
             })();
             if let Some(trait_item_use_scope) = trait_item_use_scope {
-                res.extend(
+                sink.extend(
                     t.items_with_supertraits(sema.db)
                         .into_iter()
                         .filter_map(|item| {
@@ -203,11 +1197,62 @@ fn highlight_references(
                         .map(|FileReference { category, range, .. }| HighlightedRange {
                             range,
                             category,
+                            kind: HighlightKind::Reference,
+                        }),
+                );
+            }
+            // highlight the implementing types when on the trait declaration
+            if config.trait_impls && on_trait_name.is_some() {
+                sink.extend(
+                    hir::Impl::all_for_trait(sema.db, t)
+                        .into_iter()
+                        .filter_map(|impl_| impl_.source(sema.db))
+                        .filter(|source| source.file_id == file_id.into())
+                        .filter_map(|source| source.value.self_ty())
+                        .map(|self_ty| HighlightedRange {
+                            range: self_ty.syntax().text_range(),
+                            category: None,
+                            kind: HighlightKind::Reference,
+                        }),
+                );
+            }
+        }
+
+        if let Definition::Local(local) = def {
+            if config.drop_points {
+                sink.extend(highlight_drop_point(sema, local, file_id));
+            }
+            if config.break_value_sources {
+                sink.extend(
+                    local
+                        .sources(sema.db)
+                        .iter()
+                        .filter(|source| source.file() == file_id.into())
+                        .filter_map(|source| break_value_sources(source.syntax()))
+                        .flatten()
+                        .map(|range| HighlightedRange {
+                            range,
+                            category: None,
+                            kind: HighlightKind::Break,
                         }),
                 );
             }
         }
 
+        if let Definition::Variant(variant) = def {
+            if config.discriminant_uses {
+                let uses = variant_uses
+                    .iter()
+                    .filter(|&&(it, _)| it == variant)
+                    .map(|&(_, range)| range)
+                    .collect::<Vec<_>>();
+                sink.extend(highlight_discriminant_uses(sema, variant, &uses, file_id));
+            }
+        }
+
+        if config.exclude_declaration {
+            continue;
+        }
         // highlight the defs themselves
         match def {
             Definition::Local(local) => {
@@ -215,87 +1260,559 @@ fn highlight_references(
                 local
                     .sources(sema.db)
                     .into_iter()
-                    .flat_map(|x| x.to_nav(sema.db))
-                    .filter(|decl| decl.file_id == file_id)
-                    .filter_map(|decl| decl.focus_range)
-                    .map(|range| HighlightedRange { range, category })
-                    .for_each(|x| {
-                        res.insert(x);
-                    });
+                    .flat_map(|source| match source.name() {
+                        // only the name of the binding, not the pattern it is part of
+                        Some(name) if name.file_id == file_id.into() => {
+                            vec![name.value.syntax().text_range()]
+                        }
+                        _ => source
+                            .to_nav(sema.db)
+                            .into_iter()
+                            .filter(|decl| decl.file_id == file_id)
+                            .filter_map(|decl| decl.focus_range)
+                            .collect(),
+                    })
+                    .map(|range| HighlightedRange {
+                        range,
+                        category,
+                        kind: HighlightKind::Definition,
+                    })
+                    .for_each(|x| sink.push(x));
+                if config.binding_origins {
+                    sink.extend(
+                        local
+                            .sources(sema.db)
+                            .iter()
+                            .filter(|source| source.file() == file_id.into())
+                            .filter_map(|source| binding_origin(source.syntax()))
+                            .map(|token| HighlightedRange {
+                                range: token.text_range(),
+                                category: None,
+                                kind: HighlightKind::Reference,
+                            }),
+                    );
+                }
             }
-            def => {
-                let navs = match def {
-                    Definition::Module(module) => {
-                        NavigationTarget::from_module_to_decl(sema.db, module)
-                    }
-                    def => match def.try_to_nav(sema.db) {
-                        Some(it) => it,
-                        None => continue,
-                    },
-                };
-                for nav in navs {
-                    if nav.file_id != file_id {
-                        continue;
-                    }
-                    let hl_range = nav.focus_range.map(|range| {
-                        let category = matches!(def, Definition::Local(l) if l.is_mut(sema.db))
-                            .then_some(ReferenceCategory::Write);
-                        HighlightedRange { range, category }
-                    });
-                    if let Some(hl_range) = hl_range {
-                        res.insert(hl_range);
+            Definition::GenericParam(GenericParam::LifetimeParam(lifetime_param)) => {
+                // the navigation target of a lifetime param has no focus range, so pick the
+                // lifetime out of the declaration ourselves
+                if let Some(source) = lifetime_param.source(sema.db) {
+                    if let Some(lifetime) =
+                        source.value.lifetime().filter(|_| source.file_id == file_id.into())
+                    {
+                        sink.push(HighlightedRange {
+                            range: lifetime.syntax().text_range(),
+                            category: None,
+                            kind: HighlightKind::Definition,
+                        });
                     }
                 }
             }
+            def => {
+                sink.extend(def_name_ranges(sema, def, file_id).into_iter().map(|range| {
+                    HighlightedRange { range, category: None, kind: HighlightKind::Definition }
+                }));
+            }
         }
     }
 
-    res.extend(usages);
-    if res.is_empty() {
+    if let Some(res) = highlight_format_args_arg_placeholders(sema, &token, file_id) {
+        sink.extend(res);
+    }
+    if sink.seen.is_empty() {
         None
     } else {
-        Some(res.into_iter().collect())
+        Some(())
+    }
+}
+
+/// Finds the alias of a `use foo as bar;` if `token` is the alias or one of its uses, as searching
+/// the usages of the imported item by its own name doesn't find the ones naming it `bar`.
+fn find_use_alias(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+    defs: &FxHashSet<Definition>,
+    file_id: FileId,
+) -> Option<ast::Name> {
+    let alias = token.text();
+    let def = defs.iter().exactly_one().ok().copied()?;
+    // a raw identifier still names the definition by its own name
+    if token.kind() != IDENT || def.name(sema.db)?.to_smol_str() == alias.trim_start_matches("r#") {
+        return None;
+    }
+    sema.parse(file_id)
+        .syntax()
+        .descendants()
+        .filter_map(ast::Rename::cast)
+        .filter_map(|it| it.name())
+        .find(|it| {
+            it.text() == alias
+                && it
+                    .syntax()
+                    .parent()
+                    .and_then(|it| it.parent())
+                    .and_then(ast::UseTree::cast)
+                    .and_then(|it| it.path())
+                    .and_then(|path| sema.resolve_path(&path))
+                    .map_or(false, |it| Definition::from(it) == def)
+        })
+}
+
+/// Whether `range` lies within an item of the file that is disabled by a `#[cfg]` attribute, possibly
+/// applied through `#[cfg_attr]`. Paths in such items still resolve against the enclosing module,
+/// but they are not part of the build.
+fn in_cfg_disabled_item(
+    sema: &Semantics<'_, RootDatabase>,
+    source_file: &SyntaxNode,
+    range: TextRange,
+) -> bool {
+    source_file
+        .covering_element(range)
+        .ancestors()
+        .filter_map(ast::Item::cast)
+        .filter(|item| {
+            item.attrs()
+                .any(|attr| matches!(attr.simple_name().as_deref(), Some("cfg" | "cfg_attr")))
+        })
+        .any(|item| match item {
+            ast::Item::Const(it) => sema.to_def(&it).is_none(),
+            ast::Item::Enum(it) => sema.to_def(&it).is_none(),
+            ast::Item::Fn(it) => sema.to_def(&it).is_none(),
+            ast::Item::Impl(it) => sema.to_def(&it).is_none(),
+            ast::Item::Module(it) => sema.to_def(&it).is_none(),
+            ast::Item::Static(it) => sema.to_def(&it).is_none(),
+            ast::Item::Struct(it) => sema.to_def(&it).is_none(),
+            ast::Item::Trait(it) => sema.to_def(&it).is_none(),
+            ast::Item::TypeAlias(it) => sema.to_def(&it).is_none(),
+            ast::Item::Union(it) => sema.to_def(&it).is_none(),
+            _ => false,
+        })
+}
+
+/// Highlights the intra-doc links in the doc comments of the file that resolve to one of `defs`.
+fn highlight_doc_link_references(
+    sema: &Semantics<'_, RootDatabase>,
+    defs: &FxHashSet<Definition>,
+    file_id: FileId,
+) -> Vec<HighlightedRange> {
+    let src_file_id = file_id.into();
+    sema.parse(file_id)
+        .syntax()
+        .descendants()
+        .filter_map(|node| doc_attributes(sema, &node))
+        .filter_map(|(attributes, owner)| {
+            let (docs, doc_mapping) = docs_with_rangemap(sema.db, &attributes)?;
+            let ranges = extract_definitions_from_docs(&docs)
+                .into_iter()
+                .filter_map(|(range, link, ns)| {
+                    let mapped = doc_mapping.map(range).filter(|it| it.file_id == src_file_id)?;
+                    let def = resolve_doc_path_for_def(sema.db, owner, &link, ns)?;
+                    defs.contains(&def).then_some(mapped.value)
+                })
+                .collect::<Vec<_>>();
+            Some(ranges)
+        })
+        .flatten()
+        .map(|range| HighlightedRange { range, category: None, kind: HighlightKind::Reference })
+        .collect()
+}
+
+/// The ranges of the names of `def` in `file_id`.
+fn def_name_ranges(
+    sema: &Semantics<'_, RootDatabase>,
+    def: Definition,
+    file_id: FileId,
+) -> Vec<TextRange> {
+    def_name_file_ranges(sema, def)
+        .into_iter()
+        .filter(|range| range.file_id == file_id)
+        .map(|range| range.range)
+        .collect()
+}
+
+/// The ranges of the names of `def` in all files. Only falls back to building the navigation
+/// targets of `def` if its name can't be looked up directly, as for definitions in macro
+/// expansions.
+fn def_name_file_ranges(sema: &Semantics<'_, RootDatabase>, def: Definition) -> Vec<FileRange> {
+    if let Some(range) = def.range_for_rename(sema) {
+        return vec![range];
+    }
+    let navs = match def {
+        Definition::Module(module) => NavigationTarget::from_module_to_decl(sema.db, module),
+        def => match def.try_to_nav(sema.db) {
+            Some(it) => it,
+            None => return Vec::new(),
+        },
+    };
+    navs.into_iter()
+        .filter_map(|nav| Some(FileRange { file_id: nav.file_id, range: nav.focus_range? }))
+        .collect()
+}
+
+/// The tuple field whose type is named by `token`, like the first field for `u32` in
+/// `struct S(u32, u32);`.
+fn tuple_field_of_type(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+) -> Option<hir::Field> {
+    let name_ref = token.parent().and_then(ast::NameRef::cast)?;
+    let path = full_path_of_name_ref(&name_ref)?;
+    if path.segment()?.name_ref()? != name_ref {
+        return None;
+    }
+    let field = path.syntax().parent()?.parent().and_then(ast::TupleField::cast)?;
+    sema.to_def(&field)
+}
+
+/// Highlights the placeholders of a `format_args!` template referring to the same explicit argument
+/// as the placeholder at `offset`, together with the expression of that argument.
+fn highlight_format_args_placeholder(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+    offset: TextSize,
+    file_id: FileId,
+) -> Option<Vec<HighlightedRange>> {
+    let (string, format_args) = format_args_template(sema, token)?;
+    let placeholders = format_args_placeholders(&string, &format_args, token.text_range().start());
+    let &(_, index) = placeholders.iter().find(|(range, _)| range.contains_inclusive(offset))?;
+    let mut res: Vec<_> = placeholders
+        .iter()
+        .filter(|&&(_, it)| it == index)
+        .map(|&(range, _)| HighlightedRange {
+            range,
+            category: Some(ReferenceCategory::Read),
+            kind: HighlightKind::Reference,
+        })
+        .collect();
+    let arg = format_args.args().nth(index).and_then(|it| it.expr());
+    if let Some(arg_range) =
+        arg.and_then(|it| sema.original_range_opt(it.syntax())).filter(|it| it.file_id == file_id)
+    {
+        res.push(HighlightedRange {
+            range: arg_range.range,
+            category: None,
+            kind: HighlightKind::Reference,
+        });
+    }
+    Some(res)
+}
+
+/// Highlights the placeholders of the `format_args!` template using the explicit argument `token`
+/// belongs to.
+fn highlight_format_args_arg_placeholders(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+    file_id: FileId,
+) -> Option<Vec<HighlightedRange>> {
+    let (format_args, index) = sema
+        .descend_into_macros(DescendPreference::SameText, token.clone())
+        .into_iter()
+        .find_map(|it| {
+            let arg = it.parent_ancestors().find_map(ast::FormatArgsArg::cast)?;
+            let format_args = arg.syntax().parent().and_then(ast::FormatArgsExpr::cast)?;
+            let index = format_args.args().position(|it| it == arg)?;
+            Some((format_args, index))
+        })?;
+    let ast::Expr::Literal(literal) = format_args.template()? else {
+        return None;
+    };
+    let string = ast::String::cast(literal.token())?;
+    let template_range =
+        sema.original_range_opt(literal.syntax()).filter(|it| it.file_id == file_id)?.range;
+    Some(
+        format_args_placeholders(&string, &format_args, template_range.start())
+            .into_iter()
+            .filter(|&(_, it)| it == index)
+            .map(|(range, _)| HighlightedRange {
+                range,
+                category: Some(ReferenceCategory::Read),
+                kind: HighlightKind::Reference,
+            })
+            .collect(),
+    )
+}
+
+/// Finds the template string of a `format_args!` call `token` belongs to, mapped down into the
+/// expansion of the call.
+fn format_args_template(
+    sema: &Semantics<'_, RootDatabase>,
+    token: &SyntaxToken,
+) -> Option<(ast::String, ast::FormatArgsExpr)> {
+    ast::String::cast(token.clone())?;
+    sema.descend_into_macros(DescendPreference::SameText, token.clone()).into_iter().find_map(
+        |it| {
+            let string = ast::String::cast(it)?;
+            let literal = string.syntax().parent().and_then(ast::Literal::cast)?;
+            let format_args = literal.syntax().parent().and_then(ast::FormatArgsExpr::cast)?;
+            (format_args.template()? == ast::Expr::Literal(literal))
+                .then_some((string, format_args))
+        },
+    )
+}
+
+/// The placeholders of the template `string` of `format_args` that refer to an explicit argument,
+/// as pairs of the range of the placeholder and the index of the argument. The ranges are relative
+/// to `offset`, the start of the template in the file being highlighted. Placeholders capturing a
+/// variable implicitly are left out.
+fn format_args_placeholders(
+    string: &ast::String,
+    format_args: &ast::FormatArgsExpr,
+    offset: TextSize,
+) -> Vec<(TextRange, usize)> {
+    let args: Vec<_> = format_args.args().collect();
+    let text = string.text();
+
+    let mut placeholders = Vec::new();
+    let mut next_positional = 0;
+    // the start of the current placeholder and the argument it names explicitly, if any
+    let mut start = None;
+    let mut explicit_arg = None;
+    let mut after_open = false;
+    lex_format_specifiers(string, &mut |range, kind| {
+        match kind {
+            FormatSpecifier::Open => {
+                start = Some(range.start());
+                explicit_arg = None;
+            }
+            FormatSpecifier::Integer if after_open => {
+                explicit_arg = Some((range, text[range].parse().ok()));
+            }
+            FormatSpecifier::Identifier if after_open => {
+                let name = &text[range];
+                let index =
+                    args.iter().position(|arg| arg.name().map_or(false, |it| it.text() == name));
+                explicit_arg = Some((range, index));
+            }
+            // a `.*` precision takes the next positional argument before the value does
+            FormatSpecifier::Asterisk => next_positional += 1,
+            FormatSpecifier::Close => {
+                if let Some(start) = start.take() {
+                    let (range, index) = explicit_arg.take().unwrap_or_else(|| {
+                        next_positional += 1;
+                        (TextRange::new(start, range.end()), Some(next_positional - 1))
+                    });
+                    if let Some(index) = index {
+                        placeholders.push((range + offset, index));
+                    }
+                }
+            }
+            _ => (),
+        }
+        after_open = matches!(kind, FormatSpecifier::Open);
+    });
+    placeholders
+}
+
+/// Highlights the end of the scope the local is bound in, where it goes out of scope. That is the
+/// closing brace of the block for `let` statements and parameters, the loop body for `for` and
+/// `while let`, the then-branch for `if let` and the arm expression for match arms. When the body
+/// of a closure or match arm is not a block, its last token is highlighted instead.
+fn highlight_drop_point(
+    sema: &Semantics<'_, RootDatabase>,
+    local: hir::Local,
+    file_id: FileId,
+) -> Option<HighlightedRange> {
+    let source = local.primary_source(sema.db);
+    if source.file() != file_id.into() {
+        return None;
+    }
+    let binder = source.syntax().ancestors().find(|it| {
+        matches!(
+            it.kind(),
+            SyntaxKind::LET_STMT
+                | SyntaxKind::PARAM
+                | SyntaxKind::SELF_PARAM
+                | SyntaxKind::FOR_EXPR
+                | SyntaxKind::LET_EXPR
+                | SyntaxKind::MATCH_ARM
+        )
+    })?;
+    let scope_end = match binder.kind() {
+        SyntaxKind::LET_STMT => ast::StmtList::cast(binder.parent()?)?.r_curly_token(),
+        SyntaxKind::PARAM | SyntaxKind::SELF_PARAM => {
+            let owner = binder.parent()?.parent()?;
+            match_ast! {
+                match owner {
+                    ast::Fn(it) => it.body()?.stmt_list()?.r_curly_token(),
+                    ast::ClosureExpr(it) => expr_end(it.body()?),
+                    _ => None,
+                }
+            }
+        }
+        SyntaxKind::FOR_EXPR => {
+            ast::ForExpr::cast(binder)?.loop_body()?.stmt_list()?.r_curly_token()
+        }
+        SyntaxKind::LET_EXPR => {
+            // `if let` and `while let` conditions, possibly chained with `&&`, or match guards
+            let owner = binder.ancestors().find(|it| {
+                matches!(
+                    it.kind(),
+                    SyntaxKind::IF_EXPR | SyntaxKind::WHILE_EXPR | SyntaxKind::MATCH_ARM
+                )
+            })?;
+            match_ast! {
+                match owner {
+                    ast::IfExpr(it) => it.then_branch()?.stmt_list()?.r_curly_token(),
+                    ast::WhileExpr(it) => it.loop_body()?.stmt_list()?.r_curly_token(),
+                    ast::MatchArm(it) => expr_end(it.expr()?),
+                    _ => None,
+                }
+            }
+        }
+        SyntaxKind::MATCH_ARM => expr_end(ast::MatchArm::cast(binder)?.expr()?),
+        _ => None,
+    }?;
+    Some(HighlightedRange {
+        range: scope_end.text_range(),
+        category: None,
+        kind: HighlightKind::Reference,
+    })
+}
+
+/// The closing brace of `expr` if it is a block, its last token otherwise.
+fn expr_end(expr: ast::Expr) -> Option<SyntaxToken> {
+    match expr {
+        ast::Expr::BlockExpr(it) => it.stmt_list()?.r_curly_token(),
+        it => it.syntax().last_token(),
+    }
+}
+
+/// Finds the `break`s carrying a value out of the `loop` or labeled block that initializes the
+/// `let` statement introducing the given binding.
+fn break_value_sources(binding: &SyntaxNode) -> Option<Vec<TextRange>> {
+    let let_stmt = binding.parent().and_then(ast::LetStmt::cast)?;
+    let (label, body) = match let_stmt.initializer()? {
+        ast::Expr::LoopExpr(it) => (it.label(), it.loop_body().and_then(|it| it.stmt_list())),
+        ast::Expr::BlockExpr(it) => (Some(it.label()?), it.stmt_list()),
+        _ => return None,
+    };
+    let mut ranges = Vec::new();
+    for_each_break_and_continue_expr(label, body, &mut |expr| match expr {
+        ast::Expr::BreakExpr(break_) if break_.expr().is_some() => {
+            ranges.push(break_.syntax().text_range())
+        }
+        _ => (),
+    });
+    Some(ranges)
+}
+
+/// Finds the `let` of a `let`-`else` statement or the `if` of an `if let` expression that
+/// introduces the given binding.
+fn binding_origin(binding: &SyntaxNode) -> Option<SyntaxToken> {
+    let binder = binding.ancestors().find(|it| {
+        ast::LetStmt::can_cast(it.kind())
+            || ast::LetExpr::can_cast(it.kind())
+            || ast::MatchArm::can_cast(it.kind())
+            || ast::Param::can_cast(it.kind())
+            || ast::ForExpr::can_cast(it.kind())
+    })?;
+    match_ast! {
+        match binder {
+            ast::LetStmt(it) => it.let_else().and(it.let_token()),
+            ast::LetExpr(it) => {
+                let if_ = it.syntax().ancestors().find_map(ast::IfExpr::cast)?;
+                let condition = if_.condition()?.syntax().text_range();
+                if condition.contains_range(it.syntax().text_range()) {
+                    if_.if_token()
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
     }
 }
 
 fn highlight_exit_points(
     sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
     token: SyntaxToken,
 ) -> Option<Vec<HighlightedRange>> {
+    let _p = profile::span("highlight_exit_points");
     fn hl(
         sema: &Semantics<'_, RootDatabase>,
         def_ranges: [Option<TextRange>; 2],
+        ret_ty: Option<hir::Type>,
+        full_expr: bool,
+        // whether a `return` leaves the context, which is not the case for `try` blocks
+        is_return_target: bool,
+        // whether the context is a test function, which failing assertions exit
+        is_test: bool,
         body: Option<ast::Expr>,
     ) -> Option<Vec<HighlightedRange>> {
-        let mut highlights = Vec::new();
-        highlights.extend(
-            def_ranges
-                .into_iter()
-                .flatten()
-                .map(|range| HighlightedRange { category: None, range }),
-        );
+        let mut highlights = FxHashSet::default();
+        highlights.extend(def_ranges.into_iter().flatten().map(|range| HighlightedRange {
+            category: None,
+            range,
+            kind: HighlightKind::Exit,
+        }));
         let body = body?;
+        let mut returns = Vec::new();
         walk_expr(&body, &mut |expr| match expr {
-            ast::Expr::ReturnExpr(expr) => {
-                if let Some(token) = expr.return_token() {
-                    highlights.push(HighlightedRange { category: None, range: token.text_range() });
-                }
+            ast::Expr::ReturnExpr(expr) if is_return_target => returns.push(expr),
+            // the walk skips `try` blocks, but a `return` in them still leaves this context
+            ast::Expr::BlockExpr(block_expr) if is_return_target && is_try_block(&block_expr) => {
+                for_each_try_block_return(&block_expr, &mut |it| returns.push(it));
             }
             ast::Expr::TryExpr(try_) => {
                 if let Some(token) = try_.question_mark_token() {
-                    highlights.push(HighlightedRange { category: None, range: token.text_range() });
+                    let kind = match &ret_ty {
+                        Some(ret_ty) if is_converting_try(sema, &try_, ret_ty) => {
+                            HighlightKind::ConvertingExit
+                        }
+                        _ => HighlightKind::Exit,
+                    };
+                    highlights.insert(HighlightedRange {
+                        category: None,
+                        range: token.text_range(),
+                        kind,
+                    });
                 }
             }
             ast::Expr::MethodCallExpr(_) | ast::Expr::CallExpr(_) | ast::Expr::MacroExpr(_) => {
-                if sema.type_of_expr(&expr).map_or(false, |ty| ty.original.is_never()) {
-                    highlights.push(HighlightedRange {
+                let is_never = sema.type_of_expr(&expr).map_or(false, |ty| ty.original.is_never())
+                    || match &expr {
+                        ast::Expr::MacroExpr(mac) => {
+                            is_diverging_std_macro(sema, mac)
+                                || (is_test && is_std_assert_macro(sema, mac))
+                        }
+                        call => is_never_returning_call(sema, call),
+                    };
+                if is_never {
+                    highlights.insert(HighlightedRange {
                         category: None,
                         range: expr.syntax().text_range(),
+                        kind: HighlightKind::Exit,
+                    });
+                }
+            }
+            // a loop nothing breaks out of never finishes
+            ast::Expr::LoopExpr(ref loop_) => {
+                let is_never = sema.type_of_expr(&expr).map_or(false, |ty| ty.original.is_never());
+                if let Some(token) = loop_.loop_token().filter(|_| is_never) {
+                    highlights.insert(HighlightedRange {
+                        category: None,
+                        range: token.text_range(),
+                        kind: HighlightKind::Exit,
                     });
                 }
             }
             _ => (),
         });
+        for expr in returns {
+            let range = if full_expr {
+                Some(expr.syntax().text_range())
+            } else {
+                expr.return_token().map(|it| it.text_range())
+            };
+            if let Some(range) = range {
+                highlights.insert(HighlightedRange {
+                    category: None,
+                    range,
+                    kind: HighlightKind::Exit,
+                });
+            }
+        }
         let tail = match body {
             ast::Expr::BlockExpr(b) => b.tail_expr(),
             e => Some(e),
@@ -307,29 +1824,61 @@ fn highlight_exit_points(
                     ast::Expr::BreakExpr(b) => b
                         .break_token()
                         .map_or_else(|| tail.syntax().text_range(), |tok| tok.text_range()),
+                    // the `?` is an exit point of its own, so only highlight its operand to not
+                    // have both ranges overlap
+                    ast::Expr::TryExpr(try_) => try_
+                        .expr()
+                        .map_or_else(|| tail.syntax().text_range(), |it| it.syntax().text_range()),
                     _ => tail.syntax().text_range(),
                 };
-                highlights.push(HighlightedRange { category: None, range })
+                highlights.insert(HighlightedRange {
+                    category: None,
+                    range,
+                    kind: HighlightKind::Exit,
+                });
             });
         }
-        Some(highlights)
+        Some(highlights.into_iter().collect())
     }
     for anc in token.parent_ancestors() {
+        let ret_ty =
+            config.exit_points_distinguish_try.then(|| context_ret_ty(sema, &anc)).flatten();
         return match_ast! {
             match anc {
-                ast::Fn(fn_) => hl(sema, [fn_.fn_token().map(|it| it.text_range()), None], fn_.body().map(ast::Expr::BlockExpr)),
+                ast::Fn(fn_) => hl(
+                    sema,
+                    [fn_.fn_token().map(|it| it.text_range()), None],
+                    ret_ty,
+                    config.exit_points_full_expr,
+                    true,
+                    is_test_fn(&fn_),
+                    fn_.body().map(ast::Expr::BlockExpr),
+                ),
                 ast::ClosureExpr(closure) => hl(
                     sema,
                     closure.param_list().map_or([None; 2], |p| [p.l_paren_token().map(|it| it.text_range()), p.r_paren_token().map(|it| it.text_range())]),
+                    ret_ty,
+                    config.exit_points_full_expr,
+                    true,
+                    false,
                     closure.body()
                 ),
                 ast::BlockExpr(block_expr) => if matches!(block_expr.modifier(), Some(ast::BlockModifier::Async(_) | ast::BlockModifier::Try(_)| ast::BlockModifier::Const(_))) {
+                    let is_try = is_try_block(&block_expr);
+                    // a `return` in a `try` block leaves the enclosing context
+                    if is_try && token.kind() == T![return] {
+                        continue;
+                    }
                     hl(
                         sema,
                         [block_expr.modifier().and_then(|modifier| match modifier {
                             ast::BlockModifier::Async(t) | ast::BlockModifier::Try(t) | ast::BlockModifier::Const(t) => Some(t.text_range()),
                             _ => None,
                         }), None],
+                        ret_ty,
+                        config.exit_points_full_expr,
+                        !is_try,
+                        false,
                         Some(block_expr.into())
                     )
                 } else {
@@ -342,8 +1891,238 @@ fn highlight_exit_points(
     None
 }
 
-fn highlight_break_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
+fn is_try_block(block_expr: &ast::BlockExpr) -> bool {
+    matches!(block_expr.modifier(), Some(ast::BlockModifier::Try(_)))
+}
+
+/// Calls `cb` for the `return`s in the `try` block, including the ones in nested `try` blocks.
+fn for_each_try_block_return(block_expr: &ast::BlockExpr, cb: &mut dyn FnMut(ast::ReturnExpr)) {
+    walk_expr(&block_expr.clone().into(), &mut |expr| match expr {
+        ast::Expr::ReturnExpr(it) => cb(it),
+        ast::Expr::BlockExpr(it) if it != *block_expr && is_try_block(&it) => {
+            for_each_try_block_return(&it, cb)
+        }
+        _ => (),
+    });
+}
+
+/// Highlights the expression a `?` is applied to, that is the fallible subexpression.
+fn highlight_try_operand(token: &SyntaxToken) -> Option<HighlightedRange> {
+    if token.kind() != T![?] {
+        return None;
+    }
+    let operand = token.parent().and_then(ast::TryExpr::cast)?.expr()?;
+    Some(HighlightedRange {
+        range: operand.syntax().text_range(),
+        category: Some(ReferenceCategory::Read),
+        kind: HighlightKind::Reference,
+    })
+}
+
+/// Highlights the code skipped by the early exit the `return` or `?` token belongs to, one range
+/// for the rest of each block up to the end of the enclosing function, closure or block context.
+fn highlight_skipped_by_exit(token: &SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let exit = token.parent()?;
+    if !ast::ReturnExpr::can_cast(exit.kind()) && !ast::TryExpr::can_cast(exit.kind()) {
+        return None;
+    }
+    let mut highlights = Vec::new();
+    for anc in exit.ancestors() {
+        match_ast! {
+            match anc {
+                ast::StmtList(it) => {
+                    let exit_range = exit.text_range();
+                    let mut rest = it
+                        .syntax()
+                        .children()
+                        .skip_while(|it| !it.text_range().contains_range(exit_range))
+                        .skip(1);
+                    if let Some(first) = rest.next() {
+                        let last = rest.last().unwrap_or_else(|| first.clone());
+                        highlights.push(HighlightedRange {
+                            range: first.text_range().cover(last.text_range()),
+                            category: None,
+                            kind: HighlightKind::Skipped,
+                        });
+                    }
+                },
+                ast::BlockExpr(it) => match it.modifier() {
+                    Some(ast::BlockModifier::Async(_) | ast::BlockModifier::Const(_)) => break,
+                    // a `return` leaves the `try` block along with the function
+                    Some(ast::BlockModifier::Try(_)) if token.kind() == T![?] => break,
+                    _ => (),
+                },
+                ast::Fn(_) => break,
+                ast::ClosureExpr(_) => break,
+                _ => (),
+            }
+        }
+    }
+    Some(highlights)
+}
+
+/// Highlights the exit points of the enclosing context that lie in the branch introduced by the
+/// `if` or `else` keyword or the `=>` of a match arm.
+fn highlight_branch_exit_points(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    token: SyntaxToken,
+) -> Option<Vec<HighlightedRange>> {
+    let parent = token.parent()?;
+    let branch = match token.kind() {
+        T![if] => ast::IfExpr::cast(parent)?.then_branch()?.syntax().text_range(),
+        T![else] => match ast::IfExpr::cast(parent)?.else_branch()? {
+            ast::ElseBranch::Block(it) => it.syntax().text_range(),
+            ast::ElseBranch::IfExpr(it) => it.syntax().text_range(),
+        },
+        T![=>] => ast::MatchArm::cast(parent)?.expr()?.syntax().text_range(),
+        _ => return None,
+    };
+    // whether the tail of a branch exits depends on where the branching expression is, so filter
+    // the exit points of the whole context instead of collecting them from the branch alone
+    let mut highlights = highlight_exit_points(sema, config, token)?;
+    highlights.retain(|hl| branch.contains_range(hl.range));
+    Some(highlights)
+}
+
+/// Highlights the bindings of the parameters of the function the `fn` token belongs to.
+fn highlight_fn_params(token: &SyntaxToken) -> Option<Vec<HighlightedRange>> {
+    let param_list = token.parent().and_then(ast::Fn::cast)?.param_list()?;
+    let self_param = param_list.self_param().and_then(|it| it.name());
+    let params = param_list
+        .params()
+        .filter_map(|param| param.pat())
+        .flat_map(|pat| {
+            pat.syntax().descendants().filter_map(ast::IdentPat::cast).collect::<Vec<_>>()
+        })
+        .filter_map(|pat| pat.name());
+    Some(
+        self_param
+            .into_iter()
+            .chain(params)
+            .map(|name| HighlightedRange {
+                range: name.syntax().text_range(),
+                category: None,
+                kind: HighlightKind::Definition,
+            })
+            .collect(),
+    )
+}
+
+/// The type returned by an exit point context, if it can be determined.
+fn context_ret_ty(sema: &Semantics<'_, RootDatabase>, context: &SyntaxNode) -> Option<hir::Type> {
+    match_ast! {
+        match context {
+            ast::Fn(fn_) => Some(sema.to_def(&fn_)?.ret_type(sema.db)),
+            ast::ClosureExpr(closure) => {
+                let ty = sema.type_of_expr(&closure.into())?.original;
+                Some(ty.as_callable(sema.db)?.return_type())
+            },
+            ast::BlockExpr(block_expr) => match block_expr.modifier()? {
+                ast::BlockModifier::Try(_) => Some(sema.type_of_expr(&block_expr.into())?.original),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Whether the `?` converts the error of its operand into a different error type of the returned
+/// `Result`, instead of propagating it unchanged.
+fn is_converting_try(
+    sema: &Semantics<'_, RootDatabase>,
+    try_: &ast::TryExpr,
+    ret_ty: &hir::Type,
+) -> bool {
+    let error_ty = |ty: &hir::Type| {
+        let result = FamousDefs(sema, sema.scope(try_.syntax())?.krate()).core_result_Result()?;
+        if ty.as_adt()? != hir::Adt::Enum(result) {
+            return None;
+        }
+        ty.type_arguments().nth(1)
+    };
+    let Some(operand_ty) = try_.expr().and_then(|it| sema.type_of_expr(&it)) else {
+        return false;
+    };
+    match (error_ty(&operand_ty.original), error_ty(ret_ty)) {
+        (Some(from), Some(to)) => from != to,
+        _ => false,
+    }
+}
+
+/// Checks whether the callee of the call is declared to return `!`, like `std::process::exit`.
+fn is_never_returning_call(sema: &Semantics<'_, RootDatabase>, call: &ast::Expr) -> bool {
+    let func = match call {
+        ast::Expr::MethodCallExpr(call) => sema.resolve_method_call(call),
+        ast::Expr::CallExpr(call) => match call.expr() {
+            Some(ast::Expr::PathExpr(callee)) => {
+                match callee.path().and_then(|path| sema.resolve_path(&path)) {
+                    Some(PathResolution::Def(ModuleDef::Function(func))) => Some(func),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    func.map_or(false, |func| func.ret_type(sema.db).is_never())
+}
+
+/// Checks whether the macro call invokes one of the diverging macros of `core` or `std`, as those
+/// might not always be inferred as `!` (e.g. when their expansion fails to resolve).
+fn is_diverging_std_macro(sema: &Semantics<'_, RootDatabase>, mac: &ast::MacroExpr) -> bool {
+    is_std_macro(sema, mac, &["panic", "todo", "unimplemented", "unreachable"])
+}
+
+/// Checks whether the macro call invokes one of the assertion macros of `core` or `std`.
+fn is_std_assert_macro(sema: &Semantics<'_, RootDatabase>, mac: &ast::MacroExpr) -> bool {
+    is_std_macro(sema, mac, &["assert", "assert_eq", "assert_ne"])
+}
+
+fn is_std_macro(sema: &Semantics<'_, RootDatabase>, mac: &ast::MacroExpr, names: &[&str]) -> bool {
+    let Some(mac) = mac.macro_call().and_then(|call| sema.resolve_macro_call(&call)) else {
+        return false;
+    };
+    matches!(
+        mac.module(sema.db).krate().origin(sema.db),
+        CrateOrigin::Lang(LangCrateOrigin::Core | LangCrateOrigin::Std)
+    ) && names.contains(&mac.name(sema.db).to_smol_str().as_str())
+}
+
+/// Checks whether the function is a test, including the ones of test attributes of other crates
+/// like `#[tokio::test]`.
+fn is_test_fn(fn_: &ast::Fn) -> bool {
+    fn_.attrs().any(|attr| {
+        attr.path()
+            .and_then(|it| it.segment())
+            .and_then(|it| it.name_ref())
+            .map_or(false, |it| it.text() == "test")
+    })
+}
+
+/// Checks whether the awaited future is known to complete immediately, like `std::future::Ready`.
+fn is_ready_future(sema: &Semantics<'_, RootDatabase>, await_: &ast::AwaitExpr) -> bool {
+    let Some(adt) = await_
+        .expr()
+        .and_then(|it| sema.type_of_expr(&it))
+        .and_then(|it| it.original.strip_references().as_adt())
+    else {
+        return false;
+    };
+    matches!(
+        adt.module(sema.db).krate().origin(sema.db),
+        CrateOrigin::Lang(LangCrateOrigin::Core | LangCrateOrigin::Std)
+    ) && adt.name(sema.db).to_smol_str() == "Ready"
+}
+
+fn highlight_break_points(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    token: SyntaxToken,
+) -> Option<Vec<HighlightedRange>> {
+    let _p = profile::span("highlight_break_points");
     fn hl(
+        sema: &Semantics<'_, RootDatabase>,
         cursor_token_kind: SyntaxKind,
         token: Option<SyntaxToken>,
         label: Option<ast::Label>,
@@ -354,25 +2133,71 @@ fn highlight_break_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
             token.map(|tok| tok.text_range()),
             label.as_ref().map(|it| it.syntax().text_range()),
         );
-        highlights.extend(range.map(|range| HighlightedRange { category: None, range }));
+        highlights.extend(range.map(|range| HighlightedRange {
+            category: None,
+            range,
+            kind: HighlightKind::Break,
+        }));
+        if let Some(body) = &body {
+            let lifetime = label.as_ref().and_then(|it| it.lifetime());
+            for_each_macro_break_and_continue_expr(
+                sema,
+                lifetime.as_ref(),
+                body.syntax(),
+                false,
+                &mut |expr| {
+                    let targets_cursor_kind = match expr {
+                        ast::Expr::BreakExpr(_) => cursor_token_kind != T![continue],
+                        _ => cursor_token_kind != T![break],
+                    };
+                    if !targets_cursor_kind {
+                        return;
+                    }
+                    // the expression comes from the macro, so highlight what it originates from
+                    let range = sema.original_range(expr.syntax()).range;
+                    let hl = HighlightedRange { category: None, range, kind: HighlightKind::Break };
+                    if !highlights.contains(&hl) {
+                        highlights.push(hl);
+                    }
+                },
+            );
+        }
+        // a nested loop may shadow the label, so compare what the labels resolve to
+        let label_def = label.as_ref().and_then(|it| sema.to_def(it));
+        let targets_label = |lifetime: Option<ast::Lifetime>| match (
+            lifetime.and_then(|it| sema.resolve_label(&it)),
+            label_def,
+        ) {
+            (Some(target), Some(label)) => target == label,
+            _ => true,
+        };
         for_each_break_and_continue_expr(label, body, &mut |expr| {
-            let range: Option<TextRange> = match (cursor_token_kind, expr) {
-                (T![for] | T![while] | T![loop] | T![break], ast::Expr::BreakExpr(break_)) => {
+            let lifetime = match &expr {
+                ast::Expr::BreakExpr(break_) => break_.lifetime(),
+                ast::Expr::ContinueExpr(continue_) => continue_.lifetime(),
+                _ => None,
+            };
+            if !targets_label(lifetime) {
+                return;
+            }
+            let range: Option<TextRange> = match expr {
+                ast::Expr::BreakExpr(break_) if cursor_token_kind != T![continue] => cover_range(
+                    break_.break_token().map(|it| it.text_range()),
+                    break_.lifetime().map(|it| it.syntax().text_range()),
+                ),
+                ast::Expr::ContinueExpr(continue_) if cursor_token_kind != T![break] => {
                     cover_range(
-                        break_.break_token().map(|it| it.text_range()),
-                        break_.lifetime().map(|it| it.syntax().text_range()),
+                        continue_.continue_token().map(|it| it.text_range()),
+                        continue_.lifetime().map(|it| it.syntax().text_range()),
                     )
                 }
-                (
-                    T![for] | T![while] | T![loop] | T![continue],
-                    ast::Expr::ContinueExpr(continue_),
-                ) => cover_range(
-                    continue_.continue_token().map(|it| it.text_range()),
-                    continue_.lifetime().map(|it| it.syntax().text_range()),
-                ),
                 _ => None,
             };
-            highlights.extend(range.map(|range| HighlightedRange { category: None, range }));
+            highlights.extend(range.map(|range| HighlightedRange {
+                category: None,
+                range,
+                kind: HighlightKind::Break,
+            }));
         });
         Some(highlights)
     }
@@ -384,8 +2209,9 @@ fn highlight_break_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
             ast::LoopExpr(l) => l.label().and_then(|it| it.lifetime()),
             ast::ForExpr(f) => f.label().and_then(|it| it.lifetime()),
             ast::WhileExpr(w) => w.label().and_then(|it| it.lifetime()),
-            ast::BlockExpr(b) => Some(b.label().and_then(|it| it.lifetime())?),
-            _ => return None,
+            ast::BlockExpr(b) => b.label().and_then(|it| it.lifetime()),
+            ast::Lifetime(lt) => lt.syntax().parent().and_then(ast::Label::cast)?.lifetime(),
+            _ => None,
         }
     };
     let lbl = lbl.as_ref();
@@ -396,65 +2222,213 @@ fn highlight_break_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
         None => true,
     };
     let token_kind = token.kind();
-    for anc in token.parent_ancestors().flat_map(ast::Expr::cast) {
-        return match anc {
-            ast::Expr::LoopExpr(l) if label_matches(l.label()) => hl(
-                token_kind,
-                l.loop_token(),
-                l.label(),
-                l.loop_body().and_then(|it| it.stmt_list()),
+    let token_range = token.text_range();
+    let include_header =
+        config.break_points_include_header && matches!(token_kind, T![break] | T![continue]);
+    for anc in sema.token_ancestors_with_macros(token).flat_map(ast::Expr::cast) {
+        let (mut highlights, header) = match anc {
+            ast::Expr::LoopExpr(l) if label_matches(l.label()) => (
+                hl(
+                    sema,
+                    token_kind,
+                    l.loop_token(),
+                    l.label(),
+                    l.loop_body().and_then(|it| it.stmt_list()),
+                )?,
+                None,
             ),
-            ast::Expr::ForExpr(f) if label_matches(f.label()) => hl(
-                token_kind,
-                f.for_token(),
-                f.label(),
-                f.loop_body().and_then(|it| it.stmt_list()),
+            ast::Expr::ForExpr(f) if label_matches(f.label()) => (
+                hl(
+                    sema,
+                    token_kind,
+                    f.for_token(),
+                    f.label(),
+                    f.loop_body().and_then(|it| it.stmt_list()),
+                )?,
+                cover_range(
+                    f.pat().map(|it| it.syntax().text_range()),
+                    f.iterable().map(|it| it.syntax().text_range()),
+                ),
             ),
-            ast::Expr::WhileExpr(w) if label_matches(w.label()) => hl(
-                token_kind,
-                w.while_token(),
-                w.label(),
-                w.loop_body().and_then(|it| it.stmt_list()),
+            ast::Expr::WhileExpr(w) if label_matches(w.label()) => (
+                hl(
+                    sema,
+                    token_kind,
+                    w.while_token(),
+                    w.label(),
+                    w.loop_body().and_then(|it| it.stmt_list()),
+                )?,
+                w.condition().map(|it| it.syntax().text_range()),
             ),
             ast::Expr::BlockExpr(e) if e.label().is_some() && label_matches(e.label()) => {
-                hl(token_kind, None, e.label(), e.stmt_list())
+                (hl(sema, token_kind, None, e.label(), e.stmt_list())?, None)
             }
             _ => continue,
         };
+        if include_header {
+            highlights.extend(header.map(|range| HighlightedRange {
+                category: None,
+                range,
+                kind: HighlightKind::Break,
+            }));
+        }
+        return Some(highlights);
     }
-    None
+    // there is no target, for example because of a mistyped label, so only highlight the token
+    matches!(token_kind, T![break] | T![continue]).then(|| {
+        vec![HighlightedRange { category: None, range: token_range, kind: HighlightKind::Break }]
+    })
+}
+
+/// Calls `cb` for the break and continue expressions in the expansions of the macro calls inside
+/// `node` that target the loop or block labeled with `label`, or the one containing `node` if they
+/// are unlabeled and not `nested` in another loop.
+fn for_each_macro_break_and_continue_expr(
+    sema: &Semantics<'_, RootDatabase>,
+    label: Option<&ast::Lifetime>,
+    node: &SyntaxNode,
+    nested: bool,
+    cb: &mut dyn FnMut(ast::Expr),
+) {
+    let is_loop = |node: SyntaxNode| match ast::Expr::cast(node) {
+        Some(ast::Expr::LoopExpr(_) | ast::Expr::WhileExpr(_) | ast::Expr::ForExpr(_)) => true,
+        Some(ast::Expr::BlockExpr(it)) => it.label().is_some(),
+        _ => false,
+    };
+    for call in node.descendants().filter_map(ast::MacroCall::cast) {
+        let Some(expansion) = sema.expand(&call) else {
+            continue;
+        };
+        let nested = nested || call.syntax().ancestors().take_while(|it| it != node).any(is_loop);
+        for expr in expansion.descendants().filter_map(ast::Expr::cast) {
+            let lifetime = match &expr {
+                ast::Expr::BreakExpr(it) => it.lifetime(),
+                ast::Expr::ContinueExpr(it) => it.lifetime(),
+                _ => continue,
+            };
+            let targets_label = match lifetime {
+                Some(lifetime) => label.map_or(false, |label| {
+                    resolve_label_with_macros(sema, &lifetime)
+                        .map_or(false, |target| target.syntax() == label.syntax())
+                }),
+                None => {
+                    !nested
+                        && !expr.syntax().ancestors().take_while(|it| it != &expansion).any(is_loop)
+                }
+            };
+            if targets_label {
+                cb(expr);
+            }
+        }
+        for_each_macro_break_and_continue_expr(sema, label, &expansion, nested, cb);
+    }
+}
+
+/// Returns the lifetime of the label `lifetime` refers to. Unlike [`Semantics::resolve_label`], this
+/// looks through the macro call `lifetime` was expanded from, so that a nested label shadowing
+/// another one with the same name is told apart from it.
+fn resolve_label_with_macros(
+    sema: &Semantics<'_, RootDatabase>,
+    lifetime: &ast::Lifetime,
+) -> Option<ast::Lifetime> {
+    sema.ancestors_with_macros(lifetime.syntax().clone()).find_map(|node| {
+        let label = match ast::Expr::cast(node)? {
+            ast::Expr::LoopExpr(it) => it.label(),
+            ast::Expr::ForExpr(it) => it.label(),
+            ast::Expr::WhileExpr(it) => it.label(),
+            ast::Expr::BlockExpr(it) => it.label(),
+            _ => None,
+        };
+        label?.lifetime().filter(|it| it.text() == lifetime.text())
+    })
 }
 
-fn highlight_yield_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
+fn highlight_yield_points(
+    sema: &Semantics<'_, RootDatabase>,
+    config: &HighlightRelatedConfig,
+    token: SyntaxToken,
+) -> Option<Vec<HighlightedRange>> {
     fn hl(
-        async_token: Option<SyntaxToken>,
+        sema: &Semantics<'_, RootDatabase>,
+        cursor: TextRange,
+        skip_ready: bool,
+        chain_range: Option<TextRange>,
+        context_range: Option<TextRange>,
+        point_kind: SyntaxKind,
         body: Option<ast::Expr>,
     ) -> Option<Vec<HighlightedRange>> {
-        let mut highlights =
-            vec![HighlightedRange { category: None, range: async_token?.text_range() }];
+        let mut highlights = vec![HighlightedRange {
+            category: None,
+            range: context_range?,
+            kind: HighlightKind::Yield,
+        }];
         if let Some(body) = body {
             walk_expr(&body, &mut |expr| {
-                if let ast::Expr::AwaitExpr(expr) = expr {
-                    if let Some(token) = expr.await_token() {
-                        highlights
-                            .push(HighlightedRange { category: None, range: token.text_range() });
+                let token = match expr {
+                    ast::Expr::AwaitExpr(expr)
+                        if point_kind == T![await]
+                            && !(skip_ready && is_ready_future(sema, &expr))
+                            && chain_range.map_or(true, |it| {
+                                it.contains_range(expr.syntax().text_range())
+                            }) =>
+                    {
+                        expr.await_token()
                     }
+                    ast::Expr::YieldExpr(expr) if point_kind == T![yield] => expr.yield_token(),
+                    _ => None,
+                };
+                if let Some(token) = token {
+                    let range = token.text_range();
+                    let kind = if range == cursor {
+                        HighlightKind::PrimaryYield
+                    } else {
+                        HighlightKind::Yield
+                    };
+                    highlights.push(HighlightedRange { category: None, range, kind });
                 }
             });
         }
         Some(highlights)
     }
+    let skip_ready = config.yield_points_skip_ready;
+    let cursor = token.text_range();
+    if token.kind() == T![yield] {
+        // coroutines are closures, so the innermost closure of the enclosing function is the context
+        let closure = token
+            .parent_ancestors()
+            .take_while(|it| !ast::Fn::can_cast(it.kind()))
+            .find_map(ast::ClosureExpr::cast)?;
+        return hl(
+            sema,
+            cursor,
+            skip_ready,
+            None,
+            closure.param_list().map(|it| it.syntax().text_range()),
+            T![yield],
+            closure.body(),
+        );
+    }
+    let async_range = |token: Option<SyntaxToken>| token.map(|it| it.text_range());
+    // the statement or tail expression the awaits are restricted to
+    let chain_range = (config.yield_points_chain_only && token.kind() == T![await])
+        .then(|| {
+            token
+                .parent_ancestors()
+                .find(|it| it.parent().map_or(false, |it| ast::StmtList::can_cast(it.kind())))
+        })
+        .flatten()
+        .map(|it| it.text_range());
     for anc in token.parent_ancestors() {
         return match_ast! {
             match anc {
-                ast::Fn(fn_) => hl(fn_.async_token(), fn_.body().map(ast::Expr::BlockExpr)),
+                ast::Fn(fn_) => hl(sema, cursor, skip_ready, chain_range, async_range(fn_.async_token()), T![await], fn_.body().map(ast::Expr::BlockExpr)),
                 ast::BlockExpr(block_expr) => {
                     if block_expr.async_token().is_none() {
                         continue;
                     }
-                    hl(block_expr.async_token(), Some(block_expr.into()))
+                    hl(sema, cursor, skip_ready, chain_range, async_range(block_expr.async_token()), T![await], Some(block_expr.into()))
                 },
-                ast::ClosureExpr(closure) => hl(closure.async_token(), closure.body()),
+                ast::ClosureExpr(closure) => hl(sema, cursor, skip_ready, chain_range, async_range(closure.async_token()), T![await], closure.body()),
                 _ => continue,
             }
         };
@@ -462,6 +2436,17 @@ fn highlight_yield_points(token: SyntaxToken) -> Option<Vec<HighlightedRange>> {
     None
 }
 
+/// Drops the highlights that don't lie within `selection`, if there is one.
+fn retain_in_selection(
+    mut highlights: Vec<HighlightedRange>,
+    selection: Option<TextRange>,
+) -> Vec<HighlightedRange> {
+    if let Some(selection) = selection {
+        highlights.retain(|hl| selection.contains_range(hl.range));
+    }
+    highlights
+}
+
 fn cover_range(r0: Option<TextRange>, r1: Option<TextRange>) -> Option<TextRange> {
     match (r0, r1) {
         (Some(r0), Some(r1)) => Some(r0.cover(r1)),
@@ -471,8 +2456,46 @@ fn cover_range(r0: Option<TextRange>, r1: Option<TextRange>) -> Option<TextRange
     }
 }
 
+/// Returns the impl block whose self type is referred to by `token`, either through a `Self`
+/// keyword or by naming the self type in the impl header.
+fn self_type_impl(
+    sema: &Semantics<'_, RootDatabase>,
+    defs: &FxHashSet<Definition>,
+    token: &SyntaxToken,
+) -> Option<ast::Impl> {
+    let impl_ = token.parent_ancestors().find_map(ast::Impl::cast)?;
+    let impl_def = sema.to_def(&impl_)?;
+    let in_self_ty = impl_
+        .self_ty()
+        .map_or(false, |it| it.syntax().text_range().contains_range(token.text_range()));
+    let is_self_type = defs.iter().any(|&def| match def {
+        Definition::SelfType(it) => it == impl_def,
+        Definition::Adt(adt) => in_self_ty && impl_def.self_ty(sema.db).as_adt() == Some(adt),
+        _ => false,
+    });
+    is_self_type.then_some(impl_)
+}
+
+/// The definitions making up the self type of an impl: the self type itself and the `self`
+/// parameters of its methods.
+fn self_type_defs(sema: &Semantics<'_, RootDatabase>, impl_: &ast::Impl) -> FxHashSet<Definition> {
+    let self_params = impl_
+        .assoc_item_list()
+        .into_iter()
+        .flat_map(|it| it.assoc_items())
+        .filter_map(|item| match item {
+            ast::AssocItem::Fn(it) => it.param_list()?.self_param(),
+            _ => None,
+        })
+        .filter_map(|self_param| sema.to_def(&self_param))
+        .map(Definition::Local);
+    sema.to_def(impl_).map(Definition::SelfType).into_iter().chain(self_params).collect()
+}
+
 fn find_defs(sema: &Semantics<'_, RootDatabase>, token: SyntaxToken) -> FxHashSet<Definition> {
-    sema.descend_into_macros(DescendPreference::None, token)
+    // a macro may turn the token into several differently named ones, only the ones that kept its
+    // text refer to what the user sees
+    sema.descend_into_macros(DescendPreference::SameText, token)
         .into_iter()
         .filter_map(|token| IdentClass::classify_token(sema, &token))
         .map(IdentClass::definitions_no_ops)
@@ -482,6 +2505,8 @@ fn find_defs(sema: &Semantics<'_, RootDatabase>, token: SyntaxToken) -> FxHashSe
 
 #[cfg(test)]
 mod tests {
+    use ide_db::SymbolKind;
+
     use crate::fixture;
 
     use super::*;
@@ -492,6 +2517,33 @@ mod tests {
         references: true,
         closure_captures: true,
         yield_points: true,
+        break_points_include_header: false,
+        binding_origins: false,
+        closure_captures_in_body: false,
+        matching_brackets: false,
+        max_references: None,
+        exit_points_distinguish_try: false,
+        yield_points_skip_ready: false,
+        trait_impls: false,
+        exclude_declaration: false,
+        params_on_fn: false,
+        unsafe_operations: false,
+        return_type_usages: false,
+        drop_points: false,
+        exit_points_full_expr: false,
+        yield_points_chain_only: false,
+        yield_points_with_exits: false,
+        match_arms: false,
+        break_value_sources: false,
+        exit_points_in_branch: false,
+        references_in_strings: false,
+        exit_points_show_skipped: false,
+        references_adjacent_idents: false,
+        exit_points_highlight_try_operand: false,
+        discriminant_uses: false,
+        references_all_macro_arms: false,
+        exclude_builtin_types: false,
+        highlight_recursion: false,
     };
 
     #[track_caller]
@@ -501,9 +2553,27 @@ mod tests {
 
     #[track_caller]
     fn check_with_config(ra_fixture: &str, config: HighlightRelatedConfig) {
+        check_highlights(ra_fixture, |analysis, pos| analysis.highlight_related(config, pos));
+    }
+
+    #[track_caller]
+    fn check_at(ra_fixture: &str, kind: HighlightRelatedKind) {
+        check_highlights(ra_fixture, |analysis, pos| {
+            analysis.highlight_related_at(ENABLED_CONFIG, pos, kind)
+        });
+    }
+
+    #[track_caller]
+    fn check_highlights(
+        ra_fixture: &str,
+        highlight: impl FnOnce(
+            &crate::Analysis,
+            FilePosition,
+        ) -> crate::Cancellable<Option<Vec<HighlightedRange>>>,
+    ) {
         let (analysis, pos, annotations) = fixture::annotations(ra_fixture);
 
-        let hls = analysis.highlight_related(config, pos).unwrap().unwrap_or_default();
+        let hls = highlight(&analysis, pos).unwrap().unwrap_or_default();
 
         let mut expected = annotations
             .into_iter()
@@ -513,17 +2583,26 @@ mod tests {
         let mut actual = hls
             .into_iter()
             .map(|hl| {
-                (
-                    hl.range,
-                    hl.category.map(|it| {
-                        match it {
-                            ReferenceCategory::Read => "read",
-                            ReferenceCategory::Write => "write",
-                            ReferenceCategory::Import => "import",
-                        }
-                        .to_string()
-                    }),
-                )
+                let kind = match hl.kind {
+                    // nearly every highlight includes the definition, so only mark the other kinds
+                    HighlightKind::Reference | HighlightKind::Definition => None,
+                    HighlightKind::Capture => Some("capture"),
+                    HighlightKind::Exit => Some("exit"),
+                    HighlightKind::ConvertingExit => Some("converting-exit"),
+                    HighlightKind::Break => Some("break"),
+                    HighlightKind::Yield => Some("yield"),
+                    HighlightKind::PrimaryYield => Some("primary-yield"),
+                    HighlightKind::Skipped => Some("skipped"),
+                    HighlightKind::Recursion => Some("recursion"),
+                };
+                let category = hl.category.map(|it| match it {
+                    ReferenceCategory::Read => "read",
+                    ReferenceCategory::Write => "write",
+                    ReferenceCategory::Import => "import",
+                    ReferenceCategory::Move => "move",
+                });
+                let access = kind.into_iter().chain(category).collect::<Vec<_>>().join(" ");
+                (hl.range, (!access.is_empty()).then_some(access))
             })
             .collect::<Vec<_>>();
         actual.sort_by_key(|(range, _)| range.start());
@@ -549,453 +2628,519 @@ fn foo(t: Tuple) {
     }
 
     #[test]
-    fn test_hl_module() {
+    fn test_hl_tuple_field_from_type() {
         check(
             r#"
-//- /lib.rs
-mod foo$0;
- // ^^^
-//- /foo.rs
-struct Foo;
+struct Tuple(u32$0, u32);
+          // ^^^  ^^^
+
+fn foo(t: Tuple) {
+    t.0;
+   // ^ read
+    t.1;
+    let _: u32 = t.0;
+        // ^^^
+                // ^ read
+}
 "#,
         );
     }
 
     #[test]
-    fn test_hl_self_in_crate_root() {
+    fn test_hl_tuple_field_from_adt_type() {
         check(
             r#"
-use crate$0;
-  //^^^^^ import
-use self;
-  //^^^^ import
-mod __ {
-    use super;
-      //^^^^^ import
+struct Inner;
+    // ^^^^^
+struct W(Inner$0);
+      // ^^^^^
+
+fn foo(w: W) -> Inner {
+             // ^^^^^
+    w.0
+   // ^ read
 }
 "#,
         );
+    }
+
+    #[test]
+    fn test_hl_type_alias() {
         check(
             r#"
-//- /main.rs crate:main deps:lib
-use lib$0;
-  //^^^ import
-//- /lib.rs crate:lib
+//- minicore: option
+struct Bar;
+type Foo$0 = Bar;
+  // ^^^
+struct Wrapper<T>(T);
+
+fn foo(_: Foo, _: Wrapper<Foo>) -> Option<Wrapper<Foo>> {
+       // ^^^
+                       // ^^^
+                                               // ^^^
+    None
+}
 "#,
         );
     }
 
     #[test]
-    fn test_hl_self_in_module() {
+    fn test_hl_use_alias() {
         check(
             r#"
-//- /lib.rs
-mod foo;
-//- /foo.rs
-use self$0;
- // ^^^^ import
+//- /main.rs crate:main deps:std
+use std::io::Result as IoResult$0;
+                    // ^^^^^^^^
+fn f() -> IoResult<()> { loop {} }
+       // ^^^^^^^^
+fn g() -> IoResult<u32> { loop {} }
+       // ^^^^^^^^
+fn h() -> std::io::Result<()> { loop {} }
+//- /std.rs crate:std
+pub mod io {
+    pub type Result<T> = core::result::Result<T, ()>;
+}
 "#,
         );
-    }
-
-    #[test]
-    fn test_hl_local() {
         check(
             r#"
-fn foo() {
-    let mut bar = 3;
-         // ^^^ write
-    bar$0;
- // ^^^ read
+//- /main.rs crate:main deps:std
+use std::io::Result as IoResult;
+                    // ^^^^^^^^
+fn f() -> IoResult$0<()> { loop {} }
+       // ^^^^^^^^
+fn g() -> IoResult<u32> { loop {} }
+       // ^^^^^^^^
+//- /std.rs crate:std
+pub mod io {
+    pub type Result<T> = core::result::Result<T, ()>;
 }
 "#,
         );
-    }
-
-    #[test]
-    fn test_hl_local_in_attr() {
         check(
             r#"
-//- proc_macros: identity
-#[proc_macros::identity]
-fn foo() {
-    let mut bar = 3;
-         // ^^^ write
-    bar$0;
- // ^^^ read
+//- /main.rs crate:main deps:std
+use std::io::Result$0 as IoResult;
+          // ^^^^^^ import
+fn f() -> IoResult<()> { loop {} }
+fn h() -> std::io::Result<()> { loop {} }
+                // ^^^^^^
+//- /std.rs crate:std
+pub mod io {
+    pub type Result<T> = core::result::Result<T, ()>;
 }
 "#,
         );
     }
 
     #[test]
-    fn test_multi_macro_usage() {
+    fn test_hl_use_alias_shadowed_in_other_module() {
         check(
             r#"
-macro_rules! foo {
-    ($ident:ident) => {
-        fn $ident() -> $ident { loop {} }
-        struct $ident;
-    }
+mod a {
+    pub struct Foo;
 }
-
-foo!(bar$0);
-  // ^^^
-fn foo() {
-    let bar: bar = bar();
-          // ^^^
-                // ^^^
+mod b {
+    pub struct Bar;
 }
+mod c {
+    use crate::b::Bar as Alias;
+}
+use a::Foo as Alias;
+           // ^^^^^
+fn f(_: Alias$0) {}
+     // ^^^^^
 "#,
         );
-        check(
-            r#"
-macro_rules! foo {
-    ($ident:ident) => {
-        fn $ident() -> $ident { loop {} }
-        struct $ident;
     }
-}
 
-foo!(bar);
-  // ^^^
-fn foo() {
-    let bar: bar$0 = bar();
+    #[test]
+    fn test_hl_use_alias_filtered() {
+        check_with_config(
+            r#"
+struct Foo;
+use Foo as Bar;
+fn f(_: Bar$0) {}
+     // ^^^
+fn g(_: Bar) {}
+     // ^^^
+"#,
+            HighlightRelatedConfig { exclude_declaration: true, ..ENABLED_CONFIG },
+        );
+        check_with_config(
+            r#"
+struct Foo;
+use Foo as Bar;
+        // ^^^
+fn f(_: Bar$0) {}
+     // ^^^
+fn g(_: Bar) {}
+fn h(_: Bar) {}
+"#,
+            HighlightRelatedConfig { max_references: Some(1), ..ENABLED_CONFIG },
+        );
+        check(
+            r#"
+//- /lib.rs cfg:feature=a
+struct Foo;
+use Foo as Bar;
+        // ^^^
+#[cfg(feature = "a")]
+fn make() -> Bar$0 { Foo }
           // ^^^
-}
+#[cfg(feature = "b")]
+fn make() -> Bar { Foo }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_yield_points() {
+    fn test_hl_raw_ident_is_not_an_alias() {
         check(
             r#"
-pub async fn foo() {
- // ^^^^^
-    let x = foo()
-        .await$0
-      // ^^^^^
-        .await;
-      // ^^^^^
-    || { 0.await };
-    (async { 0.await }).await
-                     // ^^^^^
-}
+struct r#type;
+    // ^^^^^^
+fn f(_: r#type$0) {}
+     // ^^^^^^
 "#,
         );
     }
 
     #[test]
-    fn test_hl_yield_points2() {
+    fn test_hl_references_all_macro_arms() {
+        let config = HighlightRelatedConfig { references_all_macro_arms: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+fn foo() -> u32 { 0 }
+macro_rules! m {
+    () => { fo$0o() };
+         // ^^^
+    ($foo:expr) => { foo() + $foo };
+                  // ^^^
+}
+fn f() {
+    m!();
+    foo();
+}
+"#,
+            config,
+        );
         check(
             r#"
-pub async$0 fn foo() {
- // ^^^^^
-    let x = foo()
-        .await
-      // ^^^^^
-        .await;
-      // ^^^^^
-    || { 0.await };
-    (async { 0.await }).await
-                     // ^^^^^
+fn foo() -> u32 { 0 }
+macro_rules! m {
+    () => { fo$0o() };
+    ($foo:expr) => { foo() + $foo };
+}
+fn f() {
+    m!();
+    foo();
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_let_else_yield_points() {
+    fn test_hl_macro_metavar() {
         check(
             r#"
-pub async fn foo() {
- // ^^^^^
-    let x = foo()
-        .await$0
-      // ^^^^^
-        .await;
-      // ^^^^^
-    || { 0.await };
-    let Some(_) = None else {
-        foo().await
-           // ^^^^^
+macro_rules! m {
+    ($x:ident, $y:expr) => {
+  // ^^
+        let $x = $y;
+         // ^^
+        $x + $x$0
+     // ^^
+          // ^^
+    };
+    ($x:ident) => {
+        $x
     };
-    (async { 0.await }).await
-                     // ^^^^^
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_yield_nested_fn() {
-        check(
+    fn test_hl_match_arms() {
+        let config = HighlightRelatedConfig { match_arms: true, ..ENABLED_CONFIG };
+        check_with_config(
             r#"
-async fn foo() {
-    async fn foo2() {
- // ^^^^^
-        async fn foo3() {
-            0.await
-        }
-        0.await$0
-       // ^^^^^
+enum E { A, B(u8), C }
+fn f(e: E) {
+  // ^
+    match e$0 {
+       // ^ read
+        E::A => (),
+     // ^^^^
+        E::B(n) if n > 0 => (),
+     // ^^^^^^^
+        E::B(_) | E::C => (),
+     // ^^^^^^^
+               // ^^^^
     }
-    0.await
 }
 "#,
+            config,
         );
     }
 
     #[test]
-    fn test_hl_yield_nested_async_blocks() {
-        check(
+    fn test_hl_match_arms_keep_scrutinee_highlights() {
+        let config = HighlightRelatedConfig { match_arms: true, ..ENABLED_CONFIG };
+        check_with_config(
             r#"
-async fn foo() {
-    (async {
-  // ^^^^^
-        (async {
-           0.await
-        }).await$0 }
-        // ^^^^^
-    ).await;
+//- minicore: option, try
+struct S { bar: bool }
+fn foo() -> Option<S> { None }
+  fn f() -> Option<()> {
+//^^ exit
+    match foo()?$0.bar {
+            // ^ exit
+        true => (),
+     // ^^^^
+        false => (),
+     // ^^^^^
+    }
+    None
+ // ^^^^ exit
 }
 "#,
+            config,
         );
     }
 
     #[test]
-    fn test_hl_exit_points() {
+    fn test_hl_builtin_type_uses() {
         check(
             r#"
-  fn foo() -> u32 {
-//^^
-    if true {
-        return$0 0;
-     // ^^^^^^
-    }
-
-    0?;
-  // ^
-    0xDEAD_BEEF
- // ^^^^^^^^^^^
+fn f(a: u3$02, b: u64) -> u32 {
+     // ^^^
+                     // ^^^
+    let c: u32 = 0;
+        // ^^^
+    a
 }
 "#,
         );
-    }
-
-    #[test]
-    fn test_hl_exit_points2() {
-        check(
+        check_with_config(
             r#"
-  fn foo() ->$0 u32 {
-//^^
-    if true {
-        return 0;
-     // ^^^^^^
-    }
-
-    0?;
-  // ^
-    0xDEAD_BEEF
- // ^^^^^^^^^^^
+fn f(a: u3$02, b: u64) -> u32 {
+    let c: u32 = 0;
+    a
 }
 "#,
+            HighlightRelatedConfig { exclude_builtin_types: true, ..ENABLED_CONFIG },
         );
     }
 
     #[test]
-    fn test_hl_exit_points3() {
-        check(
+    fn test_hl_references_in_doc_links() {
+        let config = HighlightRelatedConfig { references_in_strings: true, ..ENABLED_CONFIG };
+        check_with_config(
             r#"
-  fn$0 foo() -> u32 {
-//^^
-    if true {
-        return 0;
-     // ^^^^^^
+struct Foo$0;
+    // ^^^
+/** See [Foo] and [`Bar`]. */
+     // ^^^^^
+fn bar(_: Foo) {}
+       // ^^^
+struct Bar;
+"#,
+            config,
+        );
     }
 
-    0?;
-  // ^
-    0xDEAD_BEEF
- // ^^^^^^^^^^^
+    #[test]
+    fn test_hl_definition_kind() {
+        let (analysis, pos) = fixture::position(
+            r#"
+fn f(a: u32) {
+    let x$0 = a;
+    x;
 }
 "#,
         );
+        let hls = analysis.highlight_related(ENABLED_CONFIG, pos).unwrap().unwrap();
+        let kinds = hls
+            .iter()
+            .sorted_by_key(|hl| hl.range.start())
+            .map(|hl| (hl.range, hl.kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (TextRange::new(23.into(), 24.into()), HighlightKind::Definition),
+                (TextRange::new(34.into(), 35.into()), HighlightKind::Reference),
+            ]
+        );
     }
 
     #[test]
-    fn test_hl_let_else_exit_points() {
+    fn test_hl_skips_cfg_disabled_items() {
         check(
             r#"
-  fn$0 foo() -> u32 {
-//^^
-    let Some(bar) = None else {
-        return 0;
-     // ^^^^^^
-    };
-
-    0?;
-  // ^
-    0xDEAD_BEEF
- // ^^^^^^^^^^^
-}
+//- /lib.rs cfg:feature=a
+struct Foo$0;
+    // ^^^
+#[cfg(feature = "a")]
+fn make() -> Foo { Foo }
+          // ^^^
+                // ^^^
+#[cfg(feature = "b")]
+fn make() -> Foo { Foo }
+"#,
+        );
+        check(
+            r#"
+//- /lib.rs cfg:feature=a
+struct Foo$0;
+    // ^^^
+#[cfg_attr(feature = "a", cfg(feature = "b"))]
+fn make() -> Foo { Foo }
+#[cfg_attr(feature = "b", cfg(feature = "b"))]
+fn make2() -> Foo { Foo }
+           // ^^^
+                 // ^^^
 "#,
         );
     }
 
     #[test]
-    fn test_hl_prefer_ref_over_tail_exit() {
+    fn test_hl_raw_ident() {
         check(
             r#"
-fn foo() -> u32 {
-// ^^^
-    if true {
-        return 0;
-    }
-
-    0?;
-
-    foo$0()
- // ^^^
+macro_rules! id { ($($t:tt)*) => { $($t)* } }
+fn r#async$0() {}
+// ^^^^^^^
+fn f() {
+    id!(r#async());
+     // ^^^^^^^
+    r#async();
+ // ^^^^^^^
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_never_call_is_exit_point() {
-        check(
+    fn test_hl_adjacent_idents() {
+        let config = HighlightRelatedConfig { references_adjacent_idents: true, ..ENABLED_CONFIG };
+        check_with_config(
             r#"
-struct Never;
-impl Never {
-    fn never(self) -> ! { loop {} }
-}
-macro_rules! never {
-    () => { never() }
+struct S { bar: u32 }
+        // ^^^
+fn f(foo: S) -> u32 {
+  // ^^^
+    foo$0.bar
+ // ^^^ read
+     // ^^^ read
 }
-fn never() -> ! { loop {} }
-  fn foo() ->$0 u32 {
-//^^
-    never();
- // ^^^^^^^
-    never!();
- // ^^^^^^^^
-
-    Never.never();
- // ^^^^^^^^^^^^^
-
-    0
- // ^
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn f(foo: u32) -> u32 {
+  // ^^^
+    foo$0 + 1
+ // ^^^ read
 }
 "#,
+            config,
         );
     }
 
     #[test]
-    fn test_hl_inner_tail_exit_points() {
+    fn test_hl_module() {
         check(
             r#"
-  fn foo() ->$0 u32 {
-//^^
-    if true {
-        unsafe {
-            return 5;
-         // ^^^^^^
-            5
-         // ^
-        }
-    } else if false {
-        0
-     // ^
-    } else {
-        match 5 {
-            6 => 100,
-              // ^^^
-            7 => loop {
-                break 5;
-             // ^^^^^
-            }
-            8 => 'a: loop {
-                'b: loop {
-                    break 'a 5;
-                 // ^^^^^
-                    break 'b 5;
-                    break 5;
-                };
-            }
-            //
-            _ => 500,
-              // ^^^
-        }
-    }
-}
+//- /lib.rs
+mod foo$0;
+ // ^^^
+//- /foo.rs
+struct Foo;
 "#,
         );
     }
 
     #[test]
-    fn test_hl_inner_tail_exit_points_labeled_block() {
+    fn test_hl_module_path_uses() {
         check(
             r#"
-  fn foo() ->$0 u32 {
-//^^
-    'foo: {
-        break 'foo 0;
-     // ^^^^^
-        loop {
-            break;
-            break 'foo 0;
-         // ^^^^^
-        }
-        0
-     // ^
-    }
+//- /lib.rs
+mod foo;
+ // ^^^
+fn bar(_: foo::Thing) -> foo$0::Thing {
+       // ^^^
+                      // ^^^
+    loop {}
 }
+//- /foo.rs
+pub struct Thing;
 "#,
         );
     }
 
     #[test]
-    fn test_hl_inner_tail_exit_points_loops() {
+    fn test_hl_self_in_crate_root() {
         check(
             r#"
-  fn foo() ->$0 u32 {
-//^^
-    'foo: while { return 0; true } {
-               // ^^^^^^
-        break 'foo 0;
-     // ^^^^^
-        return 0;
-     // ^^^^^^
-    }
+use crate$0;
+  //^^^^^ import
+use self;
+  //^^^^ import
+mod __ {
+    use super;
+      //^^^^^ import
 }
+"#,
+        );
+        check(
+            r#"
+//- /main.rs crate:main deps:lib
+use lib$0;
+  //^^^ import
+//- /lib.rs crate:lib
 "#,
         );
     }
 
     #[test]
-    fn test_hl_break_loop() {
+    fn test_hl_path_qualifier_keywords() {
         check(
             r#"
+struct S;
 fn foo() {
-    'outer: loop {
- // ^^^^^^^^^^^^
-         break;
-      // ^^^^^
-         'inner: loop {
-            break;
-            'innermost: loop {
-                break 'outer;
-             // ^^^^^^^^^^^^
-                break 'inner;
-            }
-            break$0 'outer;
-         // ^^^^^^^^^^^^
-            break;
+    let _ = crate$0::S;
+         // ^^^^^
+    let _ = crate::S;
+         // ^^^^^
+    let _ = self::S;
+         // ^^^^
+}
+mod m {
+    fn bar() {
+        let _ = super::S;
+             // ^^^^^
+    }
+}
+"#,
+        );
+        check(
+            r#"
+mod m {
+ // ^
+    struct S;
+    fn foo() {
+        let _ = self::S;
+             // ^^^^
+    }
+    mod n {
+        fn bar() {
+            let _ = super$0::S;
+                 // ^^^^^
         }
-        break;
-     // ^^^^^
     }
 }
 "#,
@@ -1003,124 +3148,100 @@ fn foo() {
     }
 
     #[test]
-    fn test_hl_break_loop2() {
+    fn test_hl_self_in_module() {
         check(
             r#"
-fn foo() {
-    'outer: loop {
-        break;
-        'inner: loop {
-     // ^^^^^^^^^^^^
-            break;
-         // ^^^^^
-            'innermost: loop {
-                break 'outer;
-                break 'inner;
-             // ^^^^^^^^^^^^
-            }
-            break 'outer;
-            break$0;
-         // ^^^^^
-        }
-        break;
-    }
-}
+//- /lib.rs
+mod foo;
+//- /foo.rs
+use self$0;
+ // ^^^^ import
 "#,
         );
     }
 
     #[test]
-    fn test_hl_break_for() {
+    fn test_hl_local() {
         check(
             r#"
 fn foo() {
-    'outer: for _ in () {
- // ^^^^^^^^^^^
-         break;
-      // ^^^^^
-         'inner: for _ in () {
-            break;
-            'innermost: for _ in () {
-                break 'outer;
-             // ^^^^^^^^^^^^
-                break 'inner;
-            }
-            break$0 'outer;
-         // ^^^^^^^^^^^^
-            break;
-        }
-        break;
-     // ^^^^^
-    }
+    let mut bar = 3;
+         // ^^^ write
+    bar$0;
+ // ^^^ read
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_break_for_but_not_continue() {
+    fn test_hl_local_in_tuple_pat() {
         check(
             r#"
 fn foo() {
-    'outer: for _ in () {
- // ^^^^^^^^^^^
-        break;
-     // ^^^^^
-        continue;
-        'inner: for _ in () {
-            break;
-            continue;
-            'innermost: for _ in () {
-                continue 'outer;
-                break 'outer;
-             // ^^^^^^^^^^^^
-                continue 'inner;
-                break 'inner;
-            }
-            break$0 'outer;
-         // ^^^^^^^^^^^^
-            continue 'outer;
-            break;
-            continue;
-        }
-        break;
-     // ^^^^^
-        continue;
-    }
+    let (a$0, b) = (1, 2);
+      // ^
+    a + b;
+ // ^ read
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_continue_for_but_not_break() {
+    fn test_hl_shadowed_local() {
         check(
             r#"
 fn foo() {
-    'outer: for _ in () {
- // ^^^^^^^^^^^
-        break;
-        continue;
-     // ^^^^^^^^
-        'inner: for _ in () {
-            break;
-            continue;
-            'innermost: for _ in () {
-                continue 'outer;
-             // ^^^^^^^^^^^^^^^
-                break 'outer;
-                continue 'inner;
-                break 'inner;
-            }
-            break 'outer;
-            continue$0 'outer;
-         // ^^^^^^^^^^^^^^^
-            break;
-            continue;
-        }
-        break;
-        continue;
-     // ^^^^^^^^
+    let x$0 = 1;
+     // ^
+    let x = x + 1;
+         // ^ read
+    let _ = x;
+}
+"#,
+        );
+        check(
+            r#"
+fn foo() {
+    let x = 1;
+    let x$0 = x + 1;
+     // ^
+    let _ = x;
+         // ^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_chain_bindings() {
+        check(
+            r#"
+//- minicore: option, result
+fn foo(a: Option<u32>, b: Result<u32, ()>) {
+    if let Some(x$0) = a && let Ok(y) = b {
+             // ^
+        let _ = x;
+             // ^ read
+        let _ = y;
+        let _ = x + y;
+             // ^ read
+    }
+}
+"#,
+        );
+        check(
+            r#"
+//- minicore: option, result
+fn foo(a: Option<u32>, b: Result<u32, ()>) {
+    if let Some(x) = a && let Ok(y$0) = b {
+                              // ^
+        let _ = x;
+        let _ = y;
+             // ^ read
+        let _ = x + y;
+                 // ^ read
     }
 }
 "#,
@@ -1128,126 +3249,2887 @@ fn foo() {
     }
 
     #[test]
-    fn test_hl_break_and_continue() {
+    fn test_hl_local_in_attr() {
         check(
             r#"
+//- proc_macros: identity
+#[proc_macros::identity]
 fn foo() {
-    'outer: fo$0r _ in () {
- // ^^^^^^^^^^^
-        break;
-     // ^^^^^
-        continue;
-     // ^^^^^^^^
-        'inner: for _ in () {
-            break;
-            continue;
-            'innermost: for _ in () {
-                continue 'outer;
-             // ^^^^^^^^^^^^^^^
-                break 'outer;
-             // ^^^^^^^^^^^^
-                continue 'inner;
-                break 'inner;
-            }
-            break 'outer;
-         // ^^^^^^^^^^^^
-            continue 'outer;
-         // ^^^^^^^^^^^^^^^
-            break;
-            continue;
-        }
-        break;
-     // ^^^^^
-        continue;
-     // ^^^^^^^^
-    }
+    let mut bar = 3;
+         // ^^^ write
+    bar$0;
+ // ^^^ read
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_break_while() {
+    fn test_multi_macro_usage() {
         check(
             r#"
+macro_rules! foo {
+    ($ident:ident) => {
+        fn $ident() -> $ident { loop {} }
+        struct $ident;
+    }
+}
+
+foo!(bar$0);
+  // ^^^
 fn foo() {
-    'outer: while true {
- // ^^^^^^^^^^^^^
-         break;
-      // ^^^^^
-         'inner: while true {
-            break;
-            'innermost: while true {
-                break 'outer;
-             // ^^^^^^^^^^^^
-                break 'inner;
-            }
-            break$0 'outer;
-         // ^^^^^^^^^^^^
-            break;
-        }
-        break;
-     // ^^^^^
+    let bar: bar = bar();
+          // ^^^
+                // ^^^
+}
+"#,
+        );
+        check(
+            r#"
+macro_rules! foo {
+    ($ident:ident) => {
+        fn $ident() -> $ident { loop {} }
+        struct $ident;
     }
 }
+
+foo!(bar);
+  // ^^^
+fn foo() {
+    let bar: bar$0 = bar();
+          // ^^^
+}
+"#,
+        );
+        check(
+            r#"
+macro_rules! id {
+    ($($tt:tt)*) => { $($tt)* };
+}
+macro_rules! wrap {
+    ($e:expr) => { id!($e) };
+}
+fn foo() {
+    let x$0 = 0;
+     // ^
+    let _ = x;
+         // ^ read
+    let _ = id!(x + id!(x));
+             // ^ read
+                     // ^ read
+    let _ = wrap!(id!(x));
+                   // ^ read
+}
+"#,
+        );
+        check(
+            r#"
+macro_rules! id {
+    ($($tt:tt)*) => { $($tt)* };
+}
+macro_rules! wrap {
+    ($e:expr) => { id!($e) };
+}
+fn foo() {
+    let x = 0;
+     // ^
+    let _ = x;
+         // ^ read
+    let _ = id!(x + id!(x$0));
+             // ^ read
+                     // ^ read
+    let _ = wrap!(id!(x));
+                   // ^ read
+}
 "#,
         );
     }
 
     #[test]
-    fn test_hl_break_labeled_block() {
+    fn test_multi_macro_usage_prefers_same_text() {
         check(
             r#"
+//- proc_macros: shorten
+macro_rules! both {
+    ($ident:ident) => { $ident + proc_macros::shorten!($ident) };
+}
 fn foo() {
-    'outer: {
- // ^^^^^^^
-         break;
-      // ^^^^^
-         'inner: {
-            break;
-            'innermost: {
-                break 'outer;
-             // ^^^^^^^^^^^^
-                break 'inner;
-            }
-            break$0 'outer;
-         // ^^^^^^^^^^^^
-            break;
+    let f = 0;
+    let foo = 1;
+     // ^^^
+    let _ = both!(foo$0);
+               // ^^^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_multi_macro_usage_renamed_only() {
+        check(
+            r#"
+//- proc_macros: shorten
+struct Foo;
+use Foo as f;
+macro_rules! m {
+    ($i:ident) => {
+        let _ = $i;
+        let _ = proc_macros::shorten!($i);
+    };
+}
+fn main() {
+    let foo = Foo;
+     // ^^^
+    m!(foo$0);
+    // ^^^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points() {
+        check(
+            r#"
+pub async fn foo() {
+ // ^^^^^ yield
+    let x = foo()
+        .await$0
+      // ^^^^^ primary-yield
+        .await;
+      // ^^^^^ yield
+    || { 0.await };
+    (async { 0.await }).await
+                     // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points_primary() {
+        check(
+            r#"
+async fn g() {}
+pub async fn foo() {
+ // ^^^^^ yield
+    g().await;
+     // ^^^^^ yield
+    g().await$0;
+     // ^^^^^ primary-yield
+    g().await;
+     // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points2() {
+        check(
+            r#"
+pub async$0 fn foo() {
+ // ^^^^^ yield
+    let x = foo()
+        .await
+      // ^^^^^ yield
+        .await;
+      // ^^^^^ yield
+    || { 0.await };
+    (async { 0.await }).await
+                     // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_else_yield_points() {
+        check(
+            r#"
+pub async fn foo() {
+ // ^^^^^ yield
+    let x = foo()
+        .await$0
+      // ^^^^^ primary-yield
+        .await;
+      // ^^^^^ yield
+    || { 0.await };
+    let Some(_) = None else {
+        foo().await
+           // ^^^^^ yield
+    };
+    (async { 0.await }).await
+                     // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_nested_fn() {
+        check(
+            r#"
+async fn foo() {
+    async fn foo2() {
+ // ^^^^^ yield
+        async fn foo3() {
+            0.await
         }
-        break;
-     // ^^^^^
+        0.await$0
+       // ^^^^^ primary-yield
+    }
+    0.await
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_nested_async_blocks() {
+        check(
+            r#"
+async fn foo() {
+    (async {
+  // ^^^^^ yield
+        (async {
+           0.await
+        }).await$0 }
+        // ^^^^^ primary-yield
+    ).await;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_async_block_in_non_async_fn() {
+        check(
+            r#"
+//- minicore: future
+async fn bar() {}
+fn foo() -> impl core::future::Future<Output = ()> {
+    bar();
+    async { bar().await$0 }
+ // ^^^^^ yield
+               // ^^^^^ primary-yield
+}
+"#,
+        );
     }
+
+    #[test]
+    fn test_hl_yield_points_skip_nested_async() {
+        check(
+            r#"
+  async$0 fn foo() {
+//^^^^^ yield
+    (async {
+        0.await
+    }).await;
+    // ^^^^^ yield
+    let f = async || 1.await;
+    let f = async move |x: u32| x.await;
+    f(0).await;
+      // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points_skip_ready() {
+        check_with_config(
+            r#"
+//- minicore: future
+use core::future::ready;
+async fn pending() -> u32 { 0 }
+  async$0 fn foo() {
+//^^^^^ yield
+    ready(0).await;
+    pending().await;
+           // ^^^^^ yield
+}
+"#,
+            HighlightRelatedConfig { yield_points_skip_ready: true, ..ENABLED_CONFIG },
+        );
+        check(
+            r#"
+//- minicore: future
+use core::future::ready;
+  async$0 fn foo() {
+//^^^^^ yield
+    ready(0).await;
+          // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points_in_selection() {
+        let (analysis, pos, annotations) = fixture::annotations(
+            r#"
+async fn g() {}
+pub async$0 fn foo() {
+    g().await;
+    {
+        g().await;
+         // ^^^^^ yield
+        g().await;
+         // ^^^^^ yield
+    }
+    g().await;
+}
+"#,
+        );
+        let text = analysis.file_text(pos.file_id).unwrap();
+        // the inner block, which leaves out the `async` keyword
+        let start = TextSize::of(&text[..text.find("    {").unwrap() + 4]);
+        let end = TextSize::of(&text[..text.find("    }").unwrap() + 5]);
+        let selection = Some(TextRange::new(start, end));
+        let hls = analysis
+            .highlight_related_with_selection(ENABLED_CONFIG, pos, selection)
+            .unwrap()
+            .unwrap();
+        let actual =
+            hls.iter().map(|hl| hl.range).sorted_by_key(|it| it.start()).collect::<Vec<_>>();
+        let expected = annotations.into_iter().map(|(r, _)| r.range).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+
+        // without a selection, all awaits are highlighted
+        check(
+            r#"
+async fn g() {}
+pub async$0 fn foo() {
+ // ^^^^^ yield
+    g().await;
+     // ^^^^^ yield
+    {
+        g().await;
+         // ^^^^^ yield
+        g().await;
+         // ^^^^^ yield
+    }
+    g().await;
+     // ^^^^^ yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_coroutine_yield_points() {
+        check(
+            r#"
+fn foo() {
+    let coroutine = #[coroutine] || {
+                              // ^^ yield
+        yield$0 1;
+     // ^^^^^ primary-yield
+        let inner = #[coroutine] || {
+            yield 2;
+        };
+        yield 3;
+     // ^^^^^ yield
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_nested_coroutine_yield_points() {
+        check(
+            r#"
+fn foo() {
+    let coroutine = #[coroutine] || {
+        yield 1;
+        let inner = #[coroutine] |x| {
+                              // ^^^ yield
+            yield$0 x;
+         // ^^^^^ primary-yield
+        };
+        yield 3;
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points() {
+        check(
+            r#"
+  fn foo() -> u32 {
+//^^ exit
+    if true {
+        return$0 0;
+     // ^^^^^^ exit
+    }
+
+    0?;
+  // ^ exit
+    0xDEAD_BEEF
+ // ^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_skip_closures_and_items() {
+        check(
+            r#"
+  fn$0 foo() -> u32 {
+//^^ exit
+    let f = |x: Option<u32>| -> Option<u32> {
+        if x.is_none() {
+            return None;
+        }
+        Some(x? + 1)
+    };
+    fn bar() -> u32 {
+        return 1;
+    }
+    if f(None).is_some() {
+        return bar();
+     // ^^^^^^ exit
+    }
+    0
+ // ^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_distinguish_try() {
+        let config = HighlightRelatedConfig { exit_points_distinguish_try: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+//- minicore: try, result, from
+struct E1;
+struct E2;
+impl From<E1> for E2 {
+    fn from(_: E1) -> E2 { E2 }
+}
+  fn foo(a: Result<u32, E1>, b: Result<u32, E2>) -> Result<u32, E2> {
+//^^ exit
+    let x = a?;
+          // ^ converting-exit
+    let y = b?$0;
+          // ^ exit
+    Ok(x + y)
+ // ^^^^^^^^^ exit
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: try, result, from
+struct E1;
+struct E2;
+impl From<E1> for E2 {
+    fn from(_: E1) -> E2 { E2 }
+}
+fn foo(a: Result<u32, E1>) {
+    let f = |b: Result<u32, E2>| -> Result<u32, E2> {
+        let x = a?;
+              // ^ converting-exit
+        let y = b?$0;
+              // ^ exit
+        Ok(x + y)
+     // ^^^^^^^^^ exit
+    };
+}
+"#,
+            config,
+        );
+        check(
+            r#"
+//- minicore: try, result, from
+struct E1;
+struct E2;
+impl From<E1> for E2 {
+    fn from(_: E1) -> E2 { E2 }
+}
+  fn foo(a: Result<u32, E1>, b: Result<u32, E2>) -> Result<u32, E2> {
+//^^ exit
+    let x = a?;
+          // ^ exit
+    let y = b?$0;
+          // ^ exit
+    Ok(x + y)
+ // ^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_in_branch() {
+        let config = HighlightRelatedConfig { exit_points_in_branch: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+//- minicore: option, try
+fn f(a: bool, b: Option<u8>) -> Option<u8> {
+    if$0 a {
+        let x = b?;
+              // ^ exit
+        return Some(x);
+     // ^^^^^^ exit
+    } else {
+        b?;
+        None
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: option, try
+fn f(a: bool, b: Option<u8>) -> Option<u8> {
+    if a {
+        let x = b?;
+        return Some(x);
+    } else$0 {
+        b?;
+      // ^ exit
+        None
+     // ^^^^ exit
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn f(b: Option<u8>) -> u8 {
+    match b {
+        Some(x) =$0> x,
+                // ^ exit
+        None => 0,
+    }
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_show_skipped() {
+        let config = HighlightRelatedConfig { exit_points_show_skipped: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+  fn foo() {}
+  fn f(c: bool) {
+//^^ exit
+      if c {
+          return$0;
+       // ^^^^^^ exit
+          foo();
+       // ^^^^^^ skipped
+      }
+      foo();
+   // ^^^^^^ skipped
+  }
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+  fn foo() {}
+  fn f() {
+//^^ exit
+      let _ = try {
+          return$0;
+       // ^^^^^^ exit
+          foo();
+       // ^^^^^^ skipped
+      };
+      foo();
+   // ^^^^^^ skipped
+  }
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_try_operand() {
+        let config =
+            HighlightRelatedConfig { exit_points_highlight_try_operand: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+//- minicore: option, try
+  fn foo() -> Option<u32> { None }
+  fn bar(x: u32) -> Option<u32> { Some(x) }
+  fn f() -> Option<u32> {
+//^^ exit
+      bar(foo()?$0)?
+   // ^^^^^^^^^^^ exit
+   //     ^^^^^ read
+   //          ^ exit
+   //            ^ exit
+  }
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_diverging_loop() {
+        check(
+            r#"
+  fn$0 f(c: bool) -> u32 {
+//^^ exit
+      loop {
+          if c {
+              break;
+          }
+      }
+      loop {}
+   // ^^^^ exit
+  }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_params_on_fn() {
+        let config = HighlightRelatedConfig { params_on_fn: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+struct S;
+impl S {
+    fn$0 foo(&self, a: u32, (b, mut c): (u32, u32)) -> u32 {
+ // ^^ exit
+         // ^^^^
+               // ^
+                        // ^
+                               // ^
+        if a == 0 {
+            return b;
+         // ^^^^^^ exit
+        }
+        c
+     // ^ exit
+    }
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_unsafe_operations() {
+        let config = HighlightRelatedConfig { unsafe_operations: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+union U { a: u32, b: f32 }
+static mut COUNTER: u32 = 0;
+unsafe fn danger() {}
+struct S;
+impl S {
+    unsafe fn danger(&self) {}
+}
+
+fn foo(ptr: *const u32, u: U) {
+    unsafe$0 {
+ // ^^^^^^
+        let _ = *ptr;
+             // ^
+        let _ = u.a;
+               // ^
+        COUNTER += 1;
+     // ^^^^^^^
+        danger();
+     // ^^^^^^
+        S.danger();
+       // ^^^^^^
+        let _ = &ptr;
+        unsafe {
+            danger();
+        }
+        fn nested() {
+            let _ = 0;
+        }
+        let _ = || *ptr;
+                // ^
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+  unsafe$0 fn bar(ptr: *const u32) -> u32 {
+//^^^^^^
+    unsafe {
+        *ptr;
+    }
+    *ptr
+ // ^
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_mut_writes() {
+        check(
+            r#"
+fn foo() {
+    let mut$0 x = 0;
+         // ^ write
+    let y = x;
+    x = 1;
+ // ^ write
+    if y > 0 {
+        x += y;
+     // ^ write
+    }
+    let _ = x;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_mut_writes_in_subpattern() {
+        check(
+            r#"
+fn foo() {
+    let (a, mut$0 x) = (0, 0);
+             // ^ write
+    x = a;
+ // ^ write
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_mut_without_writes() {
+        check(
+            r#"
+fn foo() {
+    let mut$0 x = 0;
+         // ^ write
+    let _ = x;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_closure_param_mut_in_let_initializer() {
+        check(
+            r#"
+fn foo() {
+    let c = |mut$0 x: u32| x += 1;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_return_type_usages() {
+        check_with_config(
+            r#"
+struct Foo;
+    // ^^^
+fn foo() ->$0 Foo {
+         // ^^^
+    let _: Foo = Foo;
+        // ^^^
+              // ^^^
+    return Foo;
+        // ^^^
+}
+"#,
+            HighlightRelatedConfig { return_type_usages: true, ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_return_type_usages_falls_back_to_exit_points() {
+        check_with_config(
+            r#"
+struct Foo;
+  fn foo(foo: &Foo) ->$0 &Foo {
+//^^ exit
+    if true {
+        return foo;
+     // ^^^^^^ exit
+    }
+    foo
+ // ^^^ exit
+}
+"#,
+            HighlightRelatedConfig { return_type_usages: true, ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_tail_try_exit_point() {
+        check(
+            r#"
+//- minicore: option, try
+fn foo() -> Option<u32> { None }
+  fn bar() -> Option<u32> {
+//^^ exit
+    foo()?$0
+ // ^^^^^ exit
+      // ^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_test_fn() {
+        check(
+            r#"
+//- minicore: result, try, from, assert
+struct E;
+fn foo() -> Result<(), E> { Ok(()) }
+#[test]
+  fn$0 t() -> Result<(), E> {
+//^^ exit
+      assert!(true);
+   // ^^^^^^^^^^^^^ exit
+      foo()?;
+        // ^ exit
+      Ok(())
+   // ^^^^^^ exit
+  }
+"#,
+        );
+        check(
+            r#"
+//- minicore: assert
+  fn$0 not_a_test() {
+//^^ exit
+      assert!(true);
+  }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_try_block() {
+        check(
+            r#"
+//- minicore: option, try
+fn f(x: Option<u32>) -> Option<u32> {
+    let y: Option<u32> = try$0 {
+                      // ^^^ exit
+        if x.is_none() {
+            return None;
+        }
+        x?
+     // ^ exit
+      // ^ exit
+    };
+    y
+}
+"#,
+        );
+        check(
+            r#"
+//- minicore: option, try
+  fn f(x: Option<u32>) -> Option<u32> {
+//^^ exit
+      let y: Option<u32> = try {
+          if x.is_none() {
+              return$0 None;
+           // ^^^^^^ exit
+          }
+          x?
+      };
+      y
+   // ^ exit
+  }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_full_expr() {
+        check_with_config(
+            r#"
+  fn$0 foo(a: u32) -> u32 {
+//^^ exit
+    if a == 0 {
+        return 0;
+     // ^^^^^^^^ exit
+    }
+    a
+ // ^ exit
+}
+"#,
+            HighlightRelatedConfig { exit_points_full_expr: true, ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points_skip_try_in_async_block() {
+        check(
+            r#"
+//- minicore: option, try, future
+  fn foo() -> Option<u32> {
+//^^ exit
+    let _ = async {
+        Some(0)?;
+        None::<u32>
+    };
+    Some(1)?$0;
+        // ^ exit
+    None
+ // ^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points_chain_only() {
+        check_with_config(
+            r#"
+//- minicore: future
+async fn foo() -> u32 { 0 }
+struct S;
+impl S {
+    async fn bar(&self) -> S { S }
+}
+  async fn baz() {
+//^^^^^ yield
+    foo().await;
+    let _ = S.bar().await$0.bar().await;
+                 // ^^^^^ primary-yield
+                             // ^^^^^ yield
+    foo().await;
+    S.bar().await;
+}
+"#,
+            HighlightRelatedConfig { yield_points_chain_only: true, ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_await_field_boundary() {
+        check(
+            r#"
+//- minicore: future
+struct S { field: u32 }
+         //^^^^^
+async fn f() -> S { loop {} }
+async fn g() {
+    f().await.$0field;
+           // ^^^^^ read
+}
+"#,
+        );
+        check(
+            r#"
+//- minicore: future
+struct S { field: u32 }
+async fn f() -> S { loop {} }
+  async fn g() {
+//^^^^^ yield
+    f().await$0.field;
+     // ^^^^^ primary-yield
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_yield_points_with_exits() {
+        check_with_config(
+            r#"
+//- minicore: future
+async fn foo() -> u32 { 0 }
+  async fn bar(c: bool) -> u32 {
+//^^^^^ yield
+     // ^^ exit
+    if c {
+        return 0;
+     // ^^^^^^ exit
+    }
+    foo().await$0
+ // ^^^^^^^^^^^ exit
+       // ^^^^^ primary-yield
+}
+"#,
+            HighlightRelatedConfig { yield_points_with_exits: true, ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points2() {
+        check(
+            r#"
+  fn foo() ->$0 u32 {
+//^^ exit
+    if true {
+        return 0;
+     // ^^^^^^ exit
+    }
+
+    0?;
+  // ^ exit
+    0xDEAD_BEEF
+ // ^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_exit_points3() {
+        check(
+            r#"
+  fn$0 foo() -> u32 {
+//^^ exit
+    if true {
+        return 0;
+     // ^^^^^^ exit
+    }
+
+    0?;
+  // ^ exit
+    0xDEAD_BEEF
+ // ^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_let_else_exit_points() {
+        check(
+            r#"
+  fn$0 foo() -> u32 {
+//^^ exit
+    let Some(bar) = None else {
+        return 0;
+     // ^^^^^^ exit
+    };
+
+    0?;
+  // ^ exit
+    0xDEAD_BEEF
+ // ^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_prefer_ref_over_tail_exit() {
+        check(
+            r#"
+fn foo() -> u32 {
+// ^^^
+    if true {
+        return 0;
+    }
+
+    0?;
+
+    foo$0()
+ // ^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_never_call_is_exit_point() {
+        check(
+            r#"
+struct Never;
+impl Never {
+    fn never(self) -> ! { loop {} }
+}
+macro_rules! never {
+    () => { never() }
+}
+fn never() -> ! { loop {} }
+  fn foo() ->$0 u32 {
+//^^ exit
+    never();
+ // ^^^^^^^ exit
+    never!();
+ // ^^^^^^^^ exit
+
+    Never.never();
+ // ^^^^^^^^^^^^^ exit
+
+    0
+ // ^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_never_returning_fn_in_stmt_is_exit_point() {
+        check(
+            r#"
+//- /main.rs crate:main deps:std
+  fn$0 foo() -> u32 {
+//^^ exit
+    if true {
+        std::process::exit(1);
+     // ^^^^^^^^^^^^^^^^^^^^^ exit
+    }
+    if false {
+        std::process::abort();
+     // ^^^^^^^^^^^^^^^^^^^^^ exit
+    }
+    0
+ // ^ exit
+}
+//- /std.rs crate:std
+pub mod process {
+    pub fn exit(code: i32) -> ! { loop {} }
+    pub fn abort() -> ! { loop {} }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_panic_is_exit_point() {
+        check(
+            r#"
+//- minicore: panic
+  fn foo() ->$0 u32 {
+//^^ exit
+    if true {
+        panic!("x");
+     // ^^^^^^^^^^^ exit
+    }
+    panic!("x")
+ // ^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_diverging_macros_are_exit_points() {
+        check(
+            r#"
+//- minicore: panic
+use core::todo as not_yet;
+  fn foo() ->$0 u32 {
+//^^ exit
+    not_yet!();
+ // ^^^^^^^^^^ exit
+    unimplemented!();
+ // ^^^^^^^^^^^^^^^^ exit
+    unreachable!()
+ // ^^^^^^^^^^^^^^ exit
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_diverging_std_macros_are_recognized_by_origin() {
+        let (analysis, file_id) = fixture::file(
+            r#"
+//- minicore: panic
+use core::todo as not_yet;
+macro_rules! unreachable {
+    () => { loop {} };
+}
+fn foo() {
+    not_yet!();
+    unimplemented!();
+    core::unreachable!();
+    unreachable!();
+}
+"#,
+        );
+        let diverging = analysis
+            .with_db(|db| {
+                let sema = Semantics::new(db);
+                sema.parse(file_id)
+                    .syntax()
+                    .descendants()
+                    .filter_map(ast::MacroExpr::cast)
+                    .map(|mac| is_diverging_std_macro(&sema, &mac))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        // The local `unreachable` shadows the one of `core` and is not recognized, even though it
+        // shares its name.
+        assert_eq!(diverging, [true, true, true, false]);
+    }
+
+    #[test]
+    fn test_hl_inner_tail_exit_points() {
+        check(
+            r#"
+  fn foo() ->$0 u32 {
+//^^ exit
+    if true {
+        unsafe {
+            return 5;
+         // ^^^^^^ exit
+            5
+         // ^ exit
+        }
+    } else if false {
+        0
+     // ^ exit
+    } else {
+        match 5 {
+            6 => 100,
+              // ^^^ exit
+            7 => loop {
+                break 5;
+             // ^^^^^ exit
+            }
+            8 => 'a: loop {
+                'b: loop {
+                    break 'a 5;
+                 // ^^^^^ exit
+                    break 'b 5;
+                    break 5;
+                };
+            }
+            //
+            _ => 500,
+              // ^^^ exit
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_inner_tail_exit_points_labeled_block() {
+        check(
+            r#"
+  fn foo() ->$0 u32 {
+//^^ exit
+    'foo: {
+        break 'foo 0;
+     // ^^^^^ exit
+        loop {
+            break;
+            break 'foo 0;
+         // ^^^^^ exit
+        }
+        0
+     // ^ exit
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_inner_tail_exit_points_loops() {
+        check(
+            r#"
+  fn foo() ->$0 u32 {
+//^^ exit
+    'foo: while { return 0; true } {
+               // ^^^^^^ exit
+        break 'foo 0;
+     // ^^^^^ exit
+        return 0;
+     // ^^^^^^ exit
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_loop() {
+        check(
+            r#"
+fn foo() {
+    'outer: loop {
+ // ^^^^^^^^^^^^ break
+         break;
+      // ^^^^^ break
+         'inner: loop {
+            break;
+            'innermost: loop {
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                break 'inner;
+            }
+            break$0 'outer;
+         // ^^^^^^^^^^^^ break
+            break;
+        }
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_loop2() {
+        check(
+            r#"
+fn foo() {
+    'outer: loop {
+        break;
+        'inner: loop {
+     // ^^^^^^^^^^^^ break
+            break;
+         // ^^^^^ break
+            'innermost: loop {
+                break 'outer;
+                break 'inner;
+             // ^^^^^^^^^^^^ break
+            }
+            break 'outer;
+            break$0;
+         // ^^^^^ break
+        }
+        break;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_points_in_macro_expansion() {
+        check(
+            r#"
+macro_rules! brk {
+    ($l:lifetime) => { break $l; };
+    () => { break; };
+}
+fn foo() {
+    'outer$0: loop {
+ // ^^^^^^^^^^^^ break
+    loop {
+        brk!('outer);
+     // ^^^^^^^^^^^^ break
+        brk!();
+        break 'outer;
+     // ^^^^^^^^^^^^ break
+    }
+    brk!();
+ // ^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_shadowed_label() {
+        check(
+            r#"
+fn f() {
+    'a$0: loop {
+ // ^^^^^^^^ break
+        'a: loop {
+            break 'a;
+            continue 'a;
+        }
+        break 'a;
+     // ^^^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_shadowed_label_in_macro_expansion() {
+        check(
+            r#"
+macro_rules! brk {
+    ($l:lifetime) => { break $l; };
+}
+fn foo() {
+    'a$0: loop {
+ // ^^^^^^^^ break
+        'a: loop {
+            brk!('a);
+        }
+        brk!('a);
+     // ^^^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_missing_label() {
+        check(
+            r#"
+fn f() {
+    'a: loop {
+        break$0 'missing;
+     // ^^^^^ break
+        break 'a;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_loop_from_label() {
+        check(
+            r#"
+fn foo() {
+    'oute$0r: loop {
+ // ^^^^^^^^^^^^ break
+         break;
+      // ^^^^^ break
+         'inner: loop {
+            break;
+            continue 'outer;
+         // ^^^^^^^^^^^^^^^ break
+            'innermost: loop {
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                break 'inner;
+            }
+            break;
+        }
+        continue;
+     // ^^^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_for() {
+        check(
+            r#"
+fn foo() {
+    'outer: for _ in () {
+ // ^^^^^^^^^^^ break
+         break;
+      // ^^^^^ break
+         'inner: for _ in () {
+            break;
+            'innermost: for _ in () {
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                break 'inner;
+            }
+            break$0 'outer;
+         // ^^^^^^^^^^^^ break
+            break;
+        }
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_for_but_not_continue() {
+        check(
+            r#"
+fn foo() {
+    'outer: for _ in () {
+ // ^^^^^^^^^^^ break
+        break;
+     // ^^^^^ break
+        continue;
+        'inner: for _ in () {
+            break;
+            continue;
+            'innermost: for _ in () {
+                continue 'outer;
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                continue 'inner;
+                break 'inner;
+            }
+            break$0 'outer;
+         // ^^^^^^^^^^^^ break
+            continue 'outer;
+            break;
+            continue;
+        }
+        break;
+     // ^^^^^ break
+        continue;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_unlabeled_continue_in_inner_loop() {
+        check(
+            r#"
+fn foo() {
+    while$0 true {
+ // ^^^^^ break
+        for _ in () {
+            continue;
+            break;
+        }
+        loop {
+            if true {
+                continue;
+            }
+            break;
+        }
+        continue;
+     // ^^^^^^^^ break
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_continue_for_but_not_break() {
+        check(
+            r#"
+fn foo() {
+    'outer: for _ in () {
+ // ^^^^^^^^^^^ break
+        break;
+        continue;
+     // ^^^^^^^^ break
+        'inner: for _ in () {
+            break;
+            continue;
+            'innermost: for _ in () {
+                continue 'outer;
+             // ^^^^^^^^^^^^^^^ break
+                break 'outer;
+                continue 'inner;
+                break 'inner;
+            }
+            break 'outer;
+            continue$0 'outer;
+         // ^^^^^^^^^^^^^^^ break
+            break;
+            continue;
+        }
+        break;
+        continue;
+     // ^^^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_and_continue() {
+        check(
+            r#"
+fn foo() {
+    'outer: fo$0r _ in () {
+ // ^^^^^^^^^^^ break
+        break;
+     // ^^^^^ break
+        continue;
+     // ^^^^^^^^ break
+        'inner: for _ in () {
+            break;
+            continue;
+            'innermost: for _ in () {
+                continue 'outer;
+             // ^^^^^^^^^^^^^^^ break
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                continue 'inner;
+                break 'inner;
+            }
+            break 'outer;
+         // ^^^^^^^^^^^^ break
+            continue 'outer;
+         // ^^^^^^^^^^^^^^^ break
+            break;
+            continue;
+        }
+        break;
+     // ^^^^^ break
+        continue;
+     // ^^^^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_while() {
+        check(
+            r#"
+fn foo() {
+    'outer: while true {
+ // ^^^^^^^^^^^^^ break
+         break;
+      // ^^^^^ break
+         'inner: while true {
+            break;
+            'innermost: while true {
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                break 'inner;
+            }
+            break$0 'outer;
+         // ^^^^^^^^^^^^ break
+            break;
+        }
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_while_let_breaks_and_binding() {
+        check(
+            r#"
+fn f(mut it: impl Iterator<Item = u32>) {
+    let x = 0;
+    while$0 let Some(x) = it.next() {
+ // ^^^^^ break
+        if x == 0 {
+            continue;
+         // ^^^^^^^^ break
+        }
+        loop {
+            break;
+        }
+        if x > 5 {
+            break;
+         // ^^^^^ break
+        }
+    }
+    x;
+}
+"#,
+        );
+        check(
+            r#"
+fn f(mut it: impl Iterator<Item = u32>) {
+    let x = 0;
+    while let Some(x$0) = it.next() {
+                // ^
+        if x == 0 {
+        // ^ read
+            continue;
+        }
+        loop {
+            break;
+        }
+        if x > 5 {
+        // ^ read
+            break;
+        }
+    }
+    x;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_labeled_block() {
+        check(
+            r#"
+fn foo() {
+    'outer: {
+ // ^^^^^^^ break
+         break;
+      // ^^^^^ break
+         'inner: {
+            break;
+            'innermost: {
+                break 'outer;
+             // ^^^^^^^^^^^^ break
+                break 'inner;
+            }
+            break$0 'outer;
+         // ^^^^^^^^^^^^ break
+            break;
+        }
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_value_labeled_block() {
+        check(
+            r#"
+fn foo(c: bool) -> u32 {
+    let x = 'a$0: {
+         // ^^^ break
+        if c {
+            break 'a 42;
+         // ^^^^^^^^ break
+        }
+        let _ = 'b: {
+            break 'b 0;
+        };
+        break 'a 1;
+     // ^^^^^^^^ break
+    };
+    x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_value_sources() {
+        let config = HighlightRelatedConfig { break_value_sources: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+fn f(cond: bool) -> i32 {
+    let x$0 = loop {
+     // ^
+        if cond {
+            break 5;
+         // ^^^^^^^ break
+        }
+        loop {
+            break;
+        }
+        break 6;
+     // ^^^^^^^ break
+    };
+    x
+ // ^read
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_points_include_header() {
+        let config = HighlightRelatedConfig { break_points_include_header: true, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() {
+    while let Some(x) = None::<u32> {
+ // ^^^^^ break
+       // ^^^^^^^^^^^^^^^^^^^^^^^^^ break
+        break$0;
+     // ^^^^^ break
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    for x in [1, 2, 3] {
+ // ^^^ break
+     // ^^^^^^^^^^^^^^ break
+        continue$0;
+     // ^^^^^^^^ break
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    loop {
+ // ^^^^ break
+        break$0;
+     // ^^^^^ break
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    while$0 true {
+ // ^^^^^ break
+        break;
+     // ^^^^^ break
+    }
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_unlabeled_loop() {
+        check(
+            r#"
+fn foo() {
+    loop {
+ // ^^^^ break
+        break$0;
+     // ^^^^^ break
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_break_unlabeled_block_in_loop() {
+        check(
+            r#"
+fn foo() {
+    loop {
+ // ^^^^ break
+        {
+            break$0;
+         // ^^^^^ break
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_field_shorthand() {
+        check(
+            r#"
+struct Struct { field: u32 }
+              //^^^^^
+fn function(field: u32) {
+          //^^^^^
+    Struct { field$0 }
+           //^^^^^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_field_update_and_pattern() {
+        check(
+            r#"
+struct Struct { field$0: u32, other: u32 }
+             // ^^^^^
+fn function(field: u32, base: Struct) -> u32 {
+    let s = Struct { field, ..base };
+                  // ^^^^^ read
+    let Struct { field: renamed, .. } = s;
+              // ^^^^^ read
+    renamed
+}
+"#,
+        );
+        check(
+            r#"
+struct Struct { field: u32, other: u32 }
+fn function(field: u32, base: Struct) -> u32 {
+    let s = Struct { field, ..base };
+    let Struct { field: renamed$0, .. } = s;
+                     // ^^^^^^^
+    renamed
+ // ^^^^^^^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_field_through_deref() {
+        check(
+            r#"
+//- minicore: deref
+struct Inner { field: u32 }
+            // ^^^^^
+struct Other { field: u32 }
+struct Wrapper(Inner);
+impl core::ops::Deref for Wrapper {
+    type Target = Inner;
+    fn deref(&self) -> &Inner { &self.0 }
+}
+struct Outer { inner: Wrapper, other: Other }
+impl Outer {
+    fn f(&self) -> u32 {
+        self.inner.field$0 + self.inner.0.field + self.other.field
+                // ^^^^^ read
+                                     // ^^^^^ read
+    }
+}
+fn g(w: &&Wrapper) -> u32 { w.field }
+                           // ^^^^^ read
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_no_self_references() {
+        let config = HighlightRelatedConfig { exclude_declaration: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+fn foo() {
+    let x$0 = 5;
+    let y = x * 2;
+         // ^ read
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+struct Foo$0;
+impl Foo {}
+  // ^^^
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_local_write_accesses() {
+        check(
+            r#"
+fn foo() {
+    let mut x$0 = 0;
+         // ^ write
+    x += 1;
+ // ^ write
+    let r = &mut x;
+              // ^ write
+    let _ = &x;
+          // ^ read
+    let _ = x + 1;
+         // ^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_ref_local() {
+        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() {
+    let x$0 = 5;
+    let y = x * 2;
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_ref_local_preserved_break() {
+        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() {
+    let x$0 = 5;
+    let y = x * 2;
+
+    loop {
+        break;
+    }
+}
+"#,
+            config.clone(),
+        );
+
+        check_with_config(
+            r#"
+fn foo() {
+    let x = 5;
+    let y = x * 2;
+
+    loop$0 {
+//  ^^^^ break
+        break;
+//      ^^^^^ break
+    }
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_ref_local_preserved_yield() {
+        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+async fn foo() {
+    let x$0 = 5;
+    let y = x * 2;
+
+    0.await;
+}
+"#,
+            config.clone(),
+        );
+
+        check_with_config(
+            r#"
+    async fn foo() {
+//  ^^^^^ yield
+        let x = 5;
+        let y = x * 2;
+
+        0.await$0;
+//        ^^^^^ primary-yield
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_ref_local_preserved_exit() {
+        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() -> i32 {
+    let x$0 = 5;
+    let y = x * 2;
+
+    if true {
+        return y;
+    }
+
+    0?
+}
+"#,
+            config.clone(),
+        );
+
+        check_with_config(
+            r#"
+  fn foo() ->$0 i32 {
+//^^ exit
+    let x = 5;
+    let y = x * 2;
+
+    if true {
+        return y;
+//      ^^^^^^ exit
+    }
+
+    0?
+//   ^ exit
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_break() {
+        let config = HighlightRelatedConfig { break_points: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() {
+    loop {
+        break$0;
+    }
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_yield() {
+        let config = HighlightRelatedConfig { yield_points: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+async$0 fn foo() {
+    0.await;
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_disabled_exit() {
+        let config = HighlightRelatedConfig { exit_points: false, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+fn foo() ->$0 i32 {
+    if true {
+        return -1;
+    }
+
+    42
+}"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_lifetime() {
+        check(
+            r#"
+fn f<'a$0>(x: &'a str, y: &'a u32) -> &'a str {
+  // ^^
+          // ^^
+                      // ^^
+                                  // ^^
+    let _: &'a str = x;
+         // ^^
+    x
+}
+fn g<'a>(x: &'a str) {}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_type_param() {
+        check(
+            r#"
+fn foo<T$0: Clone>(t: T) -> Option<T>
+    // ^
+                 // ^
+                              // ^
+where
+    T: Copy,
+ // ^
+{
+    let _: T = t;
+        // ^
+    None
+}
+fn bar<T>(t: T) -> T { t }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_matching_brackets() {
+        let config = HighlightRelatedConfig { matching_brackets: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+fn foo(a: [u32; 2]) {
+    let _ = (a[0], {$0 a[1] });
+                // ^
+                       // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo(a: [u32; 2]) {
+    let _ = (a[0]$0, { a[1] });
+           // ^
+             // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    let _ = ((1), 2)$0;
+         // ^
+                // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+macro_rules! m { ($($tt:tt)*) => {} }
+fn foo() {
+    m!$0[( [ ] ) { }];
+   // ^
+               // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    let _ = {$0 1 };
+}
+"#,
+            ENABLED_CONFIG,
+        );
+    }
+
+    #[test]
+    fn test_hl_self_param() {
+        check(
+            r#"
+struct S { a: u32, b: u32 }
+impl S {
+    fn m(&self) -> u32 { 0 }
+    fn f(&self$0) -> u32 {
+       // ^^^^
+        self.a + self.b + self.m()
+     // ^^^^ read
+              // ^^^^ read
+                       // ^^^^ read
+    }
+    fn g(&self) -> u32 { self.a }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_self_type() {
+        check(
+            r#"
+struct Foo { field: u32 }
+impl Foo {
+  // ^^^
+    fn new() -> Self$0 {
+             // ^^^^
+        Self { field: 0 }
+     // ^^^^
+    }
+    fn get(&self) -> u32 {
+         // ^^^^
+        let _ = Self::new();
+             // ^^^^
+        self.field
+     // ^^^^ read
+    }
+}
+fn foo() -> Foo { Foo::new() }
+"#,
+        );
+        check(
+            r#"
+struct Foo { field: u32 }
+     //^^^
+impl Foo$0 {
+  // ^^^
+    fn new() -> Self {
+             // ^^^^
+        Self { field: 0 }
+     // ^^^^
+    }
+    fn get(&self) -> u32 {
+         // ^^^^
+        let _ = Self::new();
+             // ^^^^
+        self.field
+     // ^^^^ read
+    }
+}
+fn foo() -> Foo { Foo::new() }
+         // ^^^   ^^^
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_max_references() {
+        check_with_config(
+            r#"
+fn foo() {
+    let x$0 = 0;
+     // ^
+    let _ = x;
+         // ^ read
+    let _ = x;
+         // ^ read
+    let _ = x;
+    let _ = x;
+}
+"#,
+            HighlightRelatedConfig { max_references: Some(2), ..ENABLED_CONFIG },
+        );
+    }
+
+    #[test]
+    fn test_hl_related_with_stops_early() {
+        let (analysis, pos) = fixture::position(
+            r#"
+fn foo() {
+    let x$0 = 0;
+    let _ = x;
+    let _ = x;
+    let _ = x;
+}
+"#,
+        );
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let res = analysis
+            .highlight_related_with(ENABLED_CONFIG, pos, |_| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                ControlFlow::Break(())
+            })
+            .unwrap();
+        assert_eq!(res, Some(()));
+        assert_eq!(calls.into_inner(), 1);
+    }
+
+    #[test]
+    fn test_hl_in_files_cross_crate_def() {
+        let (analysis, pos) = fixture::position(
+            r#"
+//- /main.rs crate:main deps:foo
+use foo::Foo$0;
+fn f(_: Foo) {}
+//- /foo.rs crate:foo
+pub struct Foo;
+"#,
+        );
+        let hls = analysis.highlight_related_in_files(ENABLED_CONFIG, pos).unwrap().unwrap();
+        let actual = hls
+            .into_iter()
+            .map(|(file_id, hl)| (file_id == pos.file_id, hl.range, hl.category))
+            .sorted_by_key(|&(in_file, range, _)| (!in_file, range.start()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            vec![
+                (true, TextRange::new(9.into(), 12.into()), Some(ReferenceCategory::Import)),
+                (true, TextRange::new(22.into(), 25.into()), None),
+                (false, TextRange::new(11.into(), 14.into()), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hl_in_range() {
+        let (analysis, pos, annotations) = fixture::annotations(
+            r#"
+fn f() {
+    let x$0 = 0;
+    x;
+    {
+        x;
+     // ^ read
+        x + 1;
+     // ^ read
+    }
+    x;
+}
+"#,
+        );
+        let text = analysis.file_text(pos.file_id).unwrap();
+        // the inner block
+        let start = TextSize::of(&text[..text.find("    {").unwrap() + 4]);
+        let end = TextSize::of(&text[..text.find("    }").unwrap() + 5]);
+        let hls = analysis
+            .highlight_related_in_range(ENABLED_CONFIG, pos, TextRange::new(start, end))
+            .unwrap()
+            .unwrap();
+        let actual =
+            hls.iter().map(|hl| hl.range).sorted_by_key(|it| it.start()).collect::<Vec<_>>();
+        let expected = annotations.into_iter().map(|(r, _)| r.range).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_hl_by_def() {
+        let (analysis, pos) = fixture::position(
+            r#"
+macro_rules! both {
+    ($i:ident) => { mod $i {} fn $i() {} };
+}
+both!(fo$0o);
+fn f() {
+    foo();
+}
+"#,
+        );
+        let text = analysis.file_text(pos.file_id).unwrap();
+        let range_of = |needle: &str| {
+            let start = TextSize::of(&text[..text.find(needle).unwrap()]);
+            TextRange::at(start, TextSize::of("foo"))
+        };
+        let groups = analysis
+            .highlight_related_by_def(ENABLED_CONFIG, pos)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|group| {
+                let ranges =
+                    group.highlights.iter().map(|hl| hl.range).sorted_by_key(|it| it.start());
+                (group.nav.and_then(|it| it.kind), ranges.collect::<Vec<_>>())
+            })
+            .sorted_by_key(|&(kind, _)| kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                (Some(SymbolKind::Function), vec![range_of("foo)"), range_of("foo()")]),
+                (Some(SymbolKind::Module), vec![range_of("foo)")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hl_field_shorthand_pattern_single_highlight() {
+        check(
+            r#"
+struct S { x: u32 }
+        // ^
+fn foo(s: S) {
+    let S { x$0 } = s;
+         // ^ read
+    let _ = x;
+         // ^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_const_and_static_usages() {
+        check(
+            r#"
+mod m {
+    pub const CONST$0: u32 = 0;
+           // ^^^^^
+}
+use m::CONST;
+    // ^^^^^ import
+fn foo() {
+    let _ = CONST;
+         // ^^^^^ read
+}
+"#,
+        );
+        check(
+            r#"
+mod m {
+    pub static STATIC$0: u32 = 0;
+            // ^^^^^^
+}
+use m::STATIC;
+    // ^^^^^^ import
+fn foo() {
+    let _ = STATIC;
+         // ^^^^^^ read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_drop_points() {
+        let config = HighlightRelatedConfig { drop_points: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+fn foo() {
+    {
+        let x$0 = 0;
+         // ^
+        if true {
+            let _ = x;
+                 // ^ read
+        }
+    }
+ // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+  fn foo(x$0: u32) {
+      // ^
+    let _ = x;
+         // ^ read
+  }
+//^
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: iterator
+fn foo() {
+    for x$0 in [0] {
+     // ^
+        let _ = x;
+             // ^ read
+    }
+ // ^
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: option
+fn foo() {
+    if let Some(x$0) = Some(0) {
+             // ^
+        let _ = x;
+             // ^ read
+    } else {
+ // ^
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    match 0 {
+        x$0 => x + 1,
+     // ^
+          // ^ read
+              // ^
+    };
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+fn foo() {
+    let f = |x$0| x + 1;
+          // ^
+             // ^ read
+                 // ^
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_trait_impls() {
+        let config = HighlightRelatedConfig { trait_impls: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+trait Trait$0 {}
+   // ^^^^^
+struct A;
+struct B<T>(T);
+impl Trait for A {}
+  // ^^^^^
+            // ^
+impl<T> Trait for B<T> {}
+     // ^^^^^
+               // ^^^^
+impl A {}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+trait Trait {}
+   // ^^^^^
+struct A;
+impl Trait$0 for A {}
+  // ^^^^^
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_trait_impl_methods_same_name() {
+        check(
+            r#"
+trait Trait {
+    fn func(self) {}
+}
+trait Other {
+    fn func(self) {}
+}
+struct A;
+struct B;
+impl Trait for A {
+    fn func$0(self) {}
+     //^^^^
+}
+impl Other for B {
+    fn func(self) {}
+}
+fn main() {
+    A.func();
+    //^^^^
+    B.func();
+    Trait::func(A);
+         //^^^^
+    Other::func(B);
+}
+"#,
+        );
+        check(
+            r#"
+trait Trait {
+    fn func(self) {}
+}
+trait Other {
+    fn func$0(self) {}
+     //^^^^
+}
+struct A;
+struct B;
+impl Trait for A {
+    fn func(self) {}
+}
+impl Other for B {
+    fn func(self) {}
+     //^^^^
+}
+fn main() {
+    A.func();
+    B.func();
+    //^^^^
+    Trait::func(A);
+    Other::func(B);
+         //^^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_enum_variant() {
+        check(
+            r#"
+enum Enum {
+    Variant$0(u32),
+ // ^^^^^^^
+    Other { x: u32 },
+}
+fn foo(e: Enum) -> Enum {
+    match e {
+        Enum::Variant(x) => Enum::Variant(x),
+           // ^^^^^^^
+                               // ^^^^^^^
+        Enum::Other { x } => Enum::Other { x },
+    }
+}
+fn bar(e: &Enum) -> bool {
+    if let Enum::Variant(..) = e { true } else { false }
+              // ^^^^^^^
+}
+"#,
+        );
+        check(
+            r#"
+enum Enum {
+    Variant(u32),
+ // ^^^^^^^
+    Other { x: u32 },
+}
+fn foo(e: Enum) -> Enum {
+    match e {
+        Enum::Variant$0(x) => Enum::Variant(x),
+           // ^^^^^^^
+                               // ^^^^^^^
+        Enum::Other { x } => Enum::Other { x },
+    }
+}
+fn bar(e: &Enum) -> bool {
+    if let Enum::Variant(..) = e { true } else { false }
+              // ^^^^^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_discriminant_uses() {
+        let config = HighlightRelatedConfig { discriminant_uses: true, ..ENABLED_CONFIG };
+        check_with_config(
+            r#"
+#[repr(u8)]
+enum E {
+    A$0 = 1,
+ // ^
+     // ^
+    B = 2,
+}
+fn f() -> u8 {
+    let _ = E::A;
+            // ^
+    E::A as u8 + E::B as u8
+ // ^^^^^^^^^^ read
+ //    ^
+}
+"#,
+            config,
+        );
+    }
+
+    #[test]
+    fn test_hl_multi_local() {
+        check(
+            r#"
+fn foo((
+    foo$0
+  //^^^
+    | foo
+    //^^^
+    | foo
+    //^^^
+): ()) {
+    foo;
+  //^^^read
+    let foo;
+}
+"#,
+        );
+        check(
+            r#"
+fn foo((
+    foo
+  //^^^
+    | foo$0
+    //^^^
+    | foo
+    //^^^
+): ()) {
+    foo;
+  //^^^read
+    let foo;
+}
+"#,
+        );
+        check(
+            r#"
+fn foo((
+    foo
+  //^^^
+    | foo
+    //^^^
+    | foo
+    //^^^
+): ()) {
+    foo$0;
+  //^^^read
+    let foo;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_binding_origins() {
+        let config = HighlightRelatedConfig { binding_origins: true, ..ENABLED_CONFIG };
+
+        check_with_config(
+            r#"
+//- minicore: option
+fn foo(opt: Option<u32>) -> u32 {
+    let Some(x$0) = opt else {
+ // ^^^
+          // ^
+        return 0;
+    };
+    x
+ // ^ read
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: option
+fn foo(opt: Option<u32>) -> u32 {
+    if let Some(x) = opt {
+ // ^^
+             // ^
+        x$0
+     // ^ read
+    } else {
+        0
+    }
+}
+"#,
+            config.clone(),
+        );
+        check_with_config(
+            r#"
+//- minicore: option
+fn foo(opt: Option<u32>) -> u32 {
+    let x$0 = 0;
+     // ^
+    x
+ // ^ read
 }
 "#,
+            config,
         );
     }
 
     #[test]
-    fn test_hl_break_unlabeled_loop() {
+    fn test_hl_multi_local_in_match_arm() {
         check(
             r#"
-fn foo() {
-    loop {
- // ^^^^
-        break$0;
-     // ^^^^^
+//- minicore: result
+fn foo(res: Result<u32, u32>) -> u32 {
+    match res {
+        Ok(x$0) | Err(x) if x > 0 => x,
+        // ^
+                 // ^
+                       // ^ read
+                                // ^ read
+        Ok(x) | Err(x) => x,
     }
 }
 "#,
         );
-    }
-
-    #[test]
-    fn test_hl_break_unlabeled_block_in_loop() {
         check(
             r#"
-fn foo() {
-    loop {
- // ^^^^
-        {
-            break$0;
-         // ^^^^^
-        }
+//- minicore: result
+fn foo(res: Result<u32, u32>) -> u32 {
+    match res {
+        Ok(x) | Err(x) if x > 0 => x$0,
+        // ^
+                 // ^
+                       // ^ read
+                                // ^ read
+        Ok(x) | Err(x) => x,
     }
 }
 "#,
@@ -1255,342 +6137,424 @@ fn foo() {
     }
 
     #[test]
-    fn test_hl_field_shorthand() {
+    fn test_hl_trait_impl_methods() {
         check(
             r#"
-struct Struct { field: u32 }
-              //^^^^^
-fn function(field: u32) {
-          //^^^^^
-    Struct { field$0 }
-           //^^^^^ read
+trait Trait {
+    fn func$0(self) {}
+     //^^^^
+}
+
+impl Trait for () {
+    fn func(self) {}
+     //^^^^
+}
+
+fn main() {
+    <()>::func(());
+        //^^^^
+    ().func();
+     //^^^^
 }
 "#,
         );
-    }
+        check(
+            r#"
+trait Trait {
+    fn func(self) {}
+}
 
-    #[test]
-    fn test_hl_disabled_ref_local() {
-        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
+impl Trait for () {
+    fn func$0(self) {}
+     //^^^^
+}
 
-        check_with_config(
+fn main() {
+    <()>::func(());
+        //^^^^
+    ().func();
+     //^^^^
+}
+"#,
+        );
+        check(
             r#"
-fn foo() {
-    let x$0 = 5;
-    let y = x * 2;
+trait Trait {
+    fn func(self) {}
+}
+
+impl Trait for () {
+    fn func(self) {}
+     //^^^^
+}
+
+fn main() {
+    <()>::func(());
+        //^^^^
+    ().func$0();
+     //^^^^
 }
 "#,
-            config,
         );
     }
 
     #[test]
-    fn test_hl_disabled_ref_local_preserved_break() {
-        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
-
-        check_with_config(
+    fn test_hl_trait_impl_methods_many_impls() {
+        check(
             r#"
-fn foo() {
-    let x$0 = 5;
-    let y = x * 2;
-
-    loop {
-        break;
-    }
+trait Trait {
+    fn func$0(self);
+     //^^^^
+}
+struct A;
+struct B;
+impl Trait for A {
+    fn func(self) {}
+     //^^^^
+}
+impl Trait for B {
+    fn func(self) {}
+     //^^^^
+}
+impl Trait for () {
+    fn func(self) {}
+     //^^^^
+}
+fn main() {
+    A.func();
+    //^^^^
+    B.func();
+    //^^^^
+    ().func();
+     //^^^^
 }
 "#,
-            config.clone(),
         );
+    }
 
-        check_with_config(
+    #[test]
+    fn test_hl_glob_import() {
+        check(
             r#"
-fn foo() {
-    let x = 5;
-    let y = x * 2;
-
-    loop$0 {
-//  ^^^^
-        break;
-//      ^^^^^
-    }
+mod foo {
+    pub fn ba$0r() {}
+        // ^^^
+    pub fn baz() {}
+}
+mod other {
+    pub fn qux() {}
+}
+use foo::*;
+      // ^ import
+use other::*;
+fn f() {
+    bar();
+ // ^^^
 }
 "#,
-            config,
+        );
+        check(
+            r#"
+enum E { A, B }
+      // ^
+use E::*;
+    // ^ import
+fn f() -> E {
+    A$0
+ // ^
+}
+"#,
+        );
+        check(
+            r#"
+mod foo {
+    fn ba$0r() {}
+    // ^^^
+    pub fn baz() {}
+}
+use foo::*;
+"#,
         );
     }
 
     #[test]
-    fn test_hl_disabled_ref_local_preserved_yield() {
-        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
-
+    fn test_hl_recursion() {
+        let config = HighlightRelatedConfig { highlight_recursion: true, ..ENABLED_CONFIG };
         check_with_config(
             r#"
-async fn foo() {
-    let x$0 = 5;
-    let y = x * 2;
-
-    0.await;
+fn fi$0b(n: u32) -> u32 {
+// ^^^
+    if n < 2 {
+        return n;
+    }
+    fib(n - 1) + fib(n - 2)
+ // ^^^ recursion
+              // ^^^ recursion
+}
+fn main() {
+    fib(10);
+ // ^^^
 }
 "#,
-            config.clone(),
+            config,
         );
+    }
 
-        check_with_config(
+    #[test]
+    fn test_hl_fn_call_and_value_uses() {
+        check(
             r#"
-    async fn foo() {
-//  ^^^^^
-        let x = 5;
-        let y = x * 2;
-
-        0.await$0;
-//        ^^^^^
+fn fo$0o() {}
+// ^^^
+fn f() {
+    foo();
+ // ^^^
+    let f = foo;
+         // ^^^
 }
 "#,
-            config,
         );
     }
 
     #[test]
-    fn test_hl_disabled_ref_local_preserved_exit() {
-        let config = HighlightRelatedConfig { references: false, ..ENABLED_CONFIG };
-
-        check_with_config(
+    fn test_hl_prelude_variants() {
+        check(
             r#"
-fn foo() -> i32 {
-    let x$0 = 5;
-    let y = x * 2;
-
-    if true {
-        return y;
+//- minicore: option
+fn f(x: Option<u32>) -> Option<u32> {
+    match x {
+        So$0me(y) => Some(y),
+     // ^^^^
+                // ^^^^
+        None => None,
     }
-
-    0?
 }
 "#,
-            config.clone(),
         );
-
-        check_with_config(
+        check(
             r#"
-  fn foo() ->$0 i32 {
-//^^
-    let x = 5;
-    let y = x * 2;
-
-    if true {
-        return y;
-//      ^^^^^^
+//- minicore: option
+fn f(x: Option<u32>) -> Option<u32> {
+    match x {
+        Some(y) => Some(y),
+        No$0ne => None,
+     // ^^^^
+             // ^^^^
     }
-
-    0?
-//   ^
+}
 "#,
-            config,
         );
     }
 
     #[test]
-    fn test_hl_disabled_break() {
-        let config = HighlightRelatedConfig { break_points: false, ..ENABLED_CONFIG };
-
-        check_with_config(
+    fn test_hl_derived_trait_method() {
+        check(
             r#"
-fn foo() {
-    loop {
-        break$0;
-    }
+//- minicore: clone, derive
+#[derive(Clone)]
+      // ^^^^^
+struct S;
+fn f() {
+    S.clo$0ne();
+   // ^^^^^
+    S.clone();
+   // ^^^^^
 }
 "#,
-            config,
         );
     }
 
     #[test]
-    fn test_hl_disabled_yield() {
-        let config = HighlightRelatedConfig { yield_points: false, ..ENABLED_CONFIG };
-
-        check_with_config(
+    fn test_assoc_type_highlighting() {
+        check(
             r#"
-async$0 fn foo() {
-    0.await;
+trait Trait {
+    type Output;
+      // ^^^^^^
+}
+impl Trait for () {
+    type Output$0 = ();
+      // ^^^^^^
 }
 "#,
-            config,
         );
     }
 
     #[test]
-    fn test_hl_disabled_exit() {
-        let config = HighlightRelatedConfig { exit_points: false, ..ENABLED_CONFIG };
-
-        check_with_config(
+    fn test_closure_capture_pipe() {
+        check(
             r#"
-fn foo() ->$0 i32 {
-    if true {
-        return -1;
-    }
-
-    42
-}"#,
-            config,
+//- minicore: copy
+fn f() {
+    let x = 1;
+    //  ^ capture
+    let c = $0|y| x + y;
+    //          ^ capture
+}
+"#,
         );
     }
 
     #[test]
-    fn test_hl_multi_local() {
+    fn test_closure_capture_move() {
         check(
             r#"
-fn foo((
-    foo$0
-  //^^^
-    | foo
-    //^^^
-    | foo
-    //^^^
-): ()) {
-    foo;
-  //^^^read
-    let foo;
+//- minicore: copy
+fn f() {
+    let x = 1;
+    //  ^ capture
+    let c = move$0 |y| x + y;
+    //               ^ capture
+}
+"#,
+        );
+        check(
+            r#"
+//- minicore: copy
+struct NotCopy;
+fn f() {
+    let x = NotCopy;
+    //  ^ capture move
+    let c = move$0 || x;
+                 // ^ capture move
 }
 "#,
         );
+    }
+
+    #[test]
+    fn test_closure_capture_kinds() {
         check(
             r#"
-fn foo((
-    foo
-  //^^^
-    | foo$0
-    //^^^
-    | foo
-    //^^^
-): ()) {
-    foo;
-  //^^^read
-    let foo;
+//- minicore: copy
+fn f() {
+    let mut x = 1;
+    //      ^ capture write
+    let y = 2;
+    //  ^ capture
+    let mut c = $0|| x += y;
+                // ^ capture write
+                     // ^ capture
 }
 "#,
         );
         check(
             r#"
-fn foo((
-    foo
-  //^^^
-    | foo
-    //^^^
-    | foo
-    //^^^
-): ()) {
-    foo$0;
-  //^^^read
-    let foo;
+//- minicore: copy, drop
+struct NotCopy;
+fn f() {
+    let x = NotCopy;
+    //  ^ capture move
+    let c = $0|| drop(x);
+                 // ^ capture move
 }
 "#,
         );
     }
 
     #[test]
-    fn test_hl_trait_impl_methods() {
+    fn test_closure_capture_mode_categories() {
         check(
             r#"
-trait Trait {
-    fn func$0(self) {}
-     //^^^^
-}
-
-impl Trait for () {
-    fn func(self) {}
-     //^^^^
+//- minicore: copy, drop
+struct NotCopy;
+fn f() {
+    let mut x = 1;
+    //      ^ capture write
+    let y = NotCopy;
+    //  ^ capture move
+    let mut c = $0|| {
+        x += 1;
+     // ^ capture write
+        drop(y);
+          // ^ capture move
+    };
 }
+"#,
+        );
+    }
 
-fn main() {
-    <()>::func(());
-        //^^^^
-    ().func();
-     //^^^^
+    #[test]
+    fn test_closure_capture_nested() {
+        check(
+            r#"
+//- minicore: copy
+fn f() {
+    let captured = 1;
+    //  ^^^^^^^^ capture
+    let c = $0|| { |x| captured + x };
+                  // ^^^^^^^^ capture
 }
 "#,
         );
         check(
             r#"
-trait Trait {
-    fn func(self) {}
-}
-
-impl Trait for () {
-    fn func$0(self) {}
-     //^^^^
-}
-
-fn main() {
-    <()>::func(());
-        //^^^^
-    ().func();
-     //^^^^
+//- minicore: copy
+fn f() {
+    let captured = 1;
+    //  ^^^^^^^^ capture
+    let c = || { $0|x| captured + x };
+                  // ^^^^^^^^ capture
 }
 "#,
         );
         check(
             r#"
-trait Trait {
-    fn func(self) {}
-}
-
-impl Trait for () {
-    fn func(self) {}
-     //^^^^
-}
-
-fn main() {
-    <()>::func(());
-        //^^^^
-    ().func$0();
-     //^^^^
+//- minicore: copy
+fn f() {
+    let captured = 1;
+    let c = || captured $0| 1;
 }
 "#,
         );
     }
 
     #[test]
-    fn test_assoc_type_highlighting() {
+    fn test_closure_capture_self() {
         check(
             r#"
-trait Trait {
-    type Output;
-      // ^^^^^^
-}
-impl Trait for () {
-    type Output$0 = ();
-      // ^^^^^^
+//- minicore: copy
+struct S { field: u32 }
+impl S {
+    fn f(&self) {
+       // ^^^^ capture
+        let c = $0|| self.field;
+                // ^^^^ capture
+    }
 }
 "#,
         );
     }
 
     #[test]
-    fn test_closure_capture_pipe() {
-        check(
+    fn test_closure_capture_in_body() {
+        let config = HighlightRelatedConfig { closure_captures_in_body: true, ..ENABLED_CONFIG };
+
+        check_with_config(
             r#"
+//- minicore: copy
 fn f() {
     let x = 1;
-    //  ^
-    let c = $0|y| x + y;
-    //          ^ read
+    //  ^ capture
+    let c = move |y| x$0 + y;
+    //               ^ capture
+    x;
 }
 "#,
+            config.clone(),
         );
-    }
-
-    #[test]
-    fn test_closure_capture_move() {
-        check(
+        check_with_config(
             r#"
+//- minicore: copy
 fn f() {
     let x = 1;
-    //  ^
-    let c = move$0 |y| x + y;
-    //               ^ read
+    let c = move |y| x + y$0;
+               // ^
+                      // ^ read
+    x;
 }
 "#,
+            config,
         );
     }
 
@@ -1619,7 +6583,7 @@ fn f<T: Foo$0>(t: T) {
     t.m();
     //^
     T::C;
-     //^
+     //^ read
     T::f();
      //^
 }
@@ -1635,6 +6599,129 @@ fn f2<T: Foo>(t: T) {
         );
     }
 
+    #[test]
+    fn test_trait_highlights_assoc_item_uses_where_clause_and_impl_trait() {
+        check(
+            r#"
+trait Foo {
+    //^^^
+    const C: usize;
+    fn m(&self) {}
+}
+fn f<T>(t: T)
+where
+    T: Clone,
+    T: Foo$0,
+     //^^^
+{
+    t.m();
+    //^
+    T::C;
+     //^ read
+}
+
+fn f2<T: Foo>(t: T) {
+       //^^^
+    t.m();
+    T::C;
+}
+"#,
+        );
+        check(
+            r#"
+trait Foo {
+    //^^^
+    fn m(&self) {}
+}
+fn f(t: impl Foo$0) {
+           //^^^
+    t.m();
+    //^
+}
+
+fn f2(t: impl Foo) {
+            //^^^
+    t.m();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_trait_highlights_assoc_item_uses_in_impl() {
+        check(
+            r#"
+trait Foo {
+    //^^^
+    const C: usize;
+}
+trait Bar {
+    fn f();
+    fn g() -> usize;
+}
+impl<T: Foo$0> Bar for T {
+      //^^^
+    fn f() {
+        T::C;
+         //^ read
+    }
+    fn g() -> usize {
+        T::C
+         //^ read
+    }
+}
+fn f<T: Foo>() {
+      //^^^
+    T::C;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_trait_decl_default_body_uses() {
+        check(
+            r#"
+trait Foo$0 {
+   // ^^^
+    const C: u32;
+    fn a(&self) -> u32;
+    fn b(&self) -> u32 {
+        self.a() + Self::C
+          // ^
+                      // ^ read
+    }
+}
+fn f(x: &dyn Foo) {
+          // ^^^
+    x.a();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_trait_in_dyn_and_impl_positions() {
+        check(
+            r#"
+trait Tr {
+   // ^^
+    fn m(&self);
+}
+struct Box<T: ?Sized>(T);
+fn a(t: &dyn Tr$0) { t.m(); }
+          // ^^
+                  // ^
+fn b(_: impl Tr) {}
+          // ^^
+fn c<T: Tr>(_: T) {}
+     // ^^
+fn d(_: Box<dyn Tr>) {}
+             // ^^
+"#,
+        );
+    }
+
     #[test]
     fn implicit_format_args() {
         check(
@@ -1650,5 +6737,136 @@ fn test() {
 }
 "#,
         );
+        check(
+            r#"
+//- minicore: fmt
+fn test() {
+    let a = "foo";
+     // ^
+    format_args!("{a} {0} {} {0}", a$0);
+                // ^read
+                    // ^read
+                       // ^^read
+                           // ^read
+                                // ^read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn explicit_format_args() {
+        check(
+            r#"
+//- minicore: fmt
+fn test() {
+    let x = 0;
+    let y = 1;
+    format_args!("{0$0} {name} {} {0}", x, name = y);
+                // ^read
+                          // ^^read
+                              // ^read
+                                   // ^
+}
+"#,
+        );
+        check(
+            r#"
+//- minicore: fmt
+fn test() {
+    let x = 0;
+    let y = 1;
+    format_args!("{0} {name$0} {}", x, name = y);
+                    // ^^^^read
+                                         // ^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn format_args_named_argument() {
+        check(
+            r#"
+//- minicore: fmt
+fn test() {
+    format_args!("{name} {0} {name}", 0, name$0 = 1);
+                // ^^^^read
+                           // ^^^^read
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hl_related_at_exit_points() {
+        check_at(
+            r#"
+  fn foo() -> u32 {
+//^^ exit
+    let x$0 = 0;
+    if x == 1 {
+        return 1;
+     // ^^^^^^ exit
+    }
+    x
+ // ^ exit
+}
+"#,
+            HighlightRelatedKind::ExitPoints,
+        );
+    }
+
+    #[test]
+    fn test_hl_related_at_break_points() {
+        check_at(
+            r#"
+fn foo() {
+    loop {
+        for i in 0..3 {
+     // ^^^ break
+            if i$0 == 1 {
+                continue;
+             // ^^^^^^^^ break
+            }
+            break;
+         // ^^^^^ break
+        }
+        break;
+    }
+}
+"#,
+            HighlightRelatedKind::BreakPoints,
+        );
+    }
+
+    #[test]
+    fn test_hl_related_at_yield_points() {
+        check_at(
+            r#"
+  async fn foo() {
+//^^^^^ yield
+    let x$0 = async { 0 }.await;
+                     // ^^^^^ yield
+}
+"#,
+            HighlightRelatedKind::YieldPoints,
+        );
+    }
+
+    #[test]
+    fn test_hl_related_at_closure_captures() {
+        check_at(
+            r#"
+//- minicore: copy
+fn foo() {
+    let a = 0;
+     // ^ capture
+    let c = || a$0 + 1;
+            // ^ capture
+}
+"#,
+            HighlightRelatedKind::ClosureCaptures,
+        );
     }
 }
@@ -7210,8 +7210,8 @@ impl Iterator for S {
                                 file_id: FileId(
                                     1,
                                 ),
-                                full_range: 6850..7316,
-                                focus_range: 6894..6902,
+                                full_range: 7127..7593,
+                                focus_range: 7171..7179,
                                 name: "Iterator",
                                 kind: Trait,
                                 container_name: "iterator",
@@ -63,7 +63,7 @@ mod shuffle_crate_graph;
 mod fetch_crates;
 mod view_memory_layout;
 
-use std::ffi::OsStr;
+use std::{ffi::OsStr, ops::ControlFlow};
 
 use cfg::CfgOptions;
 use fetch_crates::CrateInfo;
@@ -87,7 +87,10 @@ pub use crate::{
     expand_macro::ExpandedMacro,
     file_structure::{StructureNode, StructureNodeKind},
     folding_ranges::{Fold, FoldKind},
-    highlight_related::{HighlightRelatedConfig, HighlightedRange},
+    highlight_related::{
+        DefHighlights, HighlightKind, HighlightRelatedConfig, HighlightRelatedKind,
+        HighlightedRange,
+    },
     hover::{
         HoverAction, HoverConfig, HoverDocFormat, HoverGotoTypeData, HoverResult,
         MemoryLayoutHoverConfig, MemoryLayoutHoverRenderKind,
@@ -590,6 +593,98 @@ impl Analysis {
         })
     }
 
+    /// Like [`Analysis::highlight_related`], but passes the ranges to `cb` as they are computed,
+    /// stopping as soon as it returns [`ControlFlow::Break`].
+    pub fn highlight_related_with(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+        mut cb: impl FnMut(HighlightedRange) -> ControlFlow<()> + std::panic::UnwindSafe,
+    ) -> Cancellable<Option<()>> {
+        self.with_db(move |db| {
+            highlight_related::highlight_related_with(
+                &Semantics::new(db),
+                config,
+                position,
+                &mut cb,
+            )
+        })
+    }
+
+    /// Like [`Analysis::highlight_related`], but also includes the definitions of the highlighted
+    /// item in other files, keying each range by its file.
+    pub fn highlight_related_in_files(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+    ) -> Cancellable<Option<Vec<(FileId, HighlightedRange)>>> {
+        self.with_db(|db| {
+            highlight_related::highlight_related_in_files(&Semantics::new(db), config, position)
+        })
+    }
+
+    /// Like [`Analysis::highlight_related`], but on an `async`, `await` or `yield` token only
+    /// highlights the yield points, the `async` keyword and, if so configured, the exit points
+    /// within the current selection, if there is one.
+    pub fn highlight_related_with_selection(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+        selection: Option<TextRange>,
+    ) -> Cancellable<Option<Vec<HighlightedRange>>> {
+        self.with_db(|db| {
+            highlight_related::highlight_related_with_selection(
+                &Semantics::new(db),
+                config,
+                position,
+                selection,
+            )
+        })
+    }
+
+    /// Like [`Analysis::highlight_related`], but only highlights what lies within `range` of the
+    /// file, for example the current selection.
+    pub fn highlight_related_in_range(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+        range: TextRange,
+    ) -> Cancellable<Option<Vec<HighlightedRange>>> {
+        self.with_db(|db| {
+            highlight_related::highlight_related_in_range(
+                &Semantics::new(db),
+                config,
+                position,
+                range,
+            )
+        })
+    }
+
+    /// Computes the references to highlight for a given position in a file, grouped by the
+    /// definitions the token at the position refers to.
+    pub fn highlight_related_by_def(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+    ) -> Cancellable<Option<Vec<DefHighlights>>> {
+        self.with_db(|db| {
+            highlight_related::highlight_related_by_def(&Semantics::new(db), config, position)
+        })
+    }
+
+    /// Computes the ranges of the given kind to highlight for a given position in a file,
+    /// regardless of the token under the cursor.
+    pub fn highlight_related_at(
+        &self,
+        config: HighlightRelatedConfig,
+        position: FilePosition,
+        kind: HighlightRelatedKind,
+    ) -> Cancellable<Option<Vec<HighlightedRange>>> {
+        self.with_db(|db| {
+            highlight_related::highlight_related_at(&Semantics::new(db), config, position, kind)
+        })
+    }
+
     /// Computes syntax highlighting for the given file range.
     pub fn highlight_range(
         &self,
@@ -1457,10 +1457,30 @@ fn main() {
             expect![[r#"
                 A Const FileId(0) 0..18 6..7
 
-                FileId(0) 42..43
+                FileId(0) 42..43 Read
                 FileId(0) 54..55
                 FileId(0) 97..98
-                FileId(0) 101..102
+                FileId(0) 101..102 Read
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_const_mut_borrow_is_read() {
+        check(
+            r#"
+const A$0: i32 = 42;
+
+fn main() {
+    let _ = &mut A;
+    let _ = A;
+}
+"#,
+            expect![[r#"
+                A Const FileId(0) 0..18 6..7
+
+                FileId(0) 49..50 Read
+                FileId(0) 64..65 Read
             "#]],
         );
     }
@@ -1732,8 +1752,8 @@ fn f<T: Trait>() {
 
                 FileId(0) 71..76
                 FileId(0) 125..130
-                FileId(0) 183..188
-                FileId(0) 206..211
+                FileId(0) 183..188 Read
+                FileId(0) 206..211 Read
             "#]],
         );
         check(
@@ -1821,7 +1841,7 @@ fn f<T: Trait>() {
             expect![[r#"
                 CONST Const FileId(0) 65..88 71..76
 
-                FileId(0) 183..188
+                FileId(0) 183..188 Read
             "#]],
         );
         check(
@@ -1906,7 +1926,7 @@ fn f<T: Trait>() {
             expect![[r#"
                 CONST Const FileId(0) 65..88 71..76
 
-                FileId(0) 183..188
+                FileId(0) 183..188 Read
             "#]],
         );
         check(
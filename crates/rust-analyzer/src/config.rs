@@ -310,16 +310,94 @@ config_data! {
         /// Controls file watching implementation.
         files_watcher: FilesWatcherDef = "\"client\"",
 
-        /// Enables highlighting of related references while the cursor is on `break`, `loop`, `while`, or `for` keywords.
+        /// Enables highlighting of the `let` of a `let`-`else` statement or the `if` of an `if let` expression
+        /// introducing a binding while the cursor is on that binding.
+        highlightRelated_bindingOrigins_enable: bool = "false",
+        /// Enables highlighting of related references while the cursor is on `break`, `loop`, `while`, or `for` keywords or on a loop label.
         highlightRelated_breakPoints_enable: bool = "true",
+        /// Whether to also highlight the condition of a `while` loop or the pattern and iterable of a `for`
+        /// loop while the cursor is on a `break` or `continue` of that loop.
+        highlightRelated_breakPoints_includeHeader: bool = "false",
+        /// Enables highlighting of the value-carrying breaks of the loop or labeled block initializing a
+        /// local if the cursor is on the local.
+        highlightRelated_breakValueSources_enable: bool = "false",
         /// Enables highlighting of all captures of a closure while the cursor is on the `|` or move keyword of a closure.
         highlightRelated_closureCaptures_enable: bool = "true",
+        /// Enables highlighting of a captured variable and its uses within the closure while the cursor is
+        /// on that variable inside the body of the closure.
+        highlightRelated_closureCaptures_inBody: bool = "false",
+        /// Enables highlighting of the explicit discriminant of an enum variant and of the `as` casts of its
+        /// uses if the cursor is on the variant.
+        highlightRelated_discriminantUses_enable: bool = "false",
+        /// Enables highlighting of the end of the scope of a local while the cursor is on any of its
+        /// references.
+        highlightRelated_dropPoints_enable: bool = "false",
+        /// Whether to only highlight the exit points reachable from a branch if the cursor is on the `if`
+        /// or `else` keyword or the `=>` of a match arm.
+        highlightRelated_exitPoints_branchOnly: bool = "false",
+        /// Distinguishes `?` operators that convert the error type from the ones that propagate it unchanged
+        /// when highlighting exit points. As the LSP only knows text, read and write highlights, the
+        /// converting ones are reported as reads, which editors show like any other read.
+        highlightRelated_exitPoints_distinguishTry: bool = "false",
         /// Enables highlighting of all exit points while the cursor is on any `return`, `?`, `fn`, or return type arrow (`->`).
         highlightRelated_exitPoints_enable: bool = "true",
+        /// Highlights the whole `return` expression including its value as an exit point instead of only
+        /// the `return` keyword.
+        highlightRelated_exitPoints_fullExpression: bool = "false",
+        /// Enables highlighting of the parameters of a function while the cursor is on its `fn` keyword.
+        highlightRelated_exitPoints_params: bool = "false",
+        /// Whether to also highlight the code skipped by an early exit while the cursor is on a `return`
+        /// keyword or `?` character. The skipped code is reported as a write highlight, so that it
+        /// stands apart from the exit points, which are text highlights.
+        highlightRelated_exitPoints_showSkipped: bool = "false",
+        /// Enables highlighting the expression a `?` is applied to when the cursor is on that `?`.
+        /// Requires `#rust-analyzer.highlightRelated.exitPoints.enable#`.
+        highlightRelated_exitPoints_tryOperand: bool = "false",
+        /// Enables highlighting of the arm patterns of a match if the cursor is on its scrutinee.
+        highlightRelated_matchArms_enable: bool = "false",
+        /// Enables highlighting of the matching delimiter while the cursor is on a brace, parenthesis or bracket.
+        highlightRelated_matchingBrackets_enable: bool = "false",
+        /// Enables highlighting of the recursive calls of a function in its own body as such if the cursor is
+        /// on the function. They are reported as read highlights, the other calls as text highlights.
+        highlightRelated_recursion_enable: bool = "false",
+        /// Whether to highlight the references of the identifiers on both sides of a punctuation token if
+        /// the cursor is right at its edge, like in `foo$0.bar`.
+        highlightRelated_references_adjacentIdentifiers: bool = "false",
+        /// Enables highlighting of the identifiers in all arms of a `macro_rules!` definition that match the
+        /// identifier under the cursor in that definition.
+        highlightRelated_references_allMacroArms: bool = "false",
+        /// Enables highlighting of the uses of a builtin type like `u32` while the cursor is on one of them.
+        highlightRelated_references_builtinTypes: bool = "true",
         /// Enables highlighting of related references while the cursor is on any identifier.
         highlightRelated_references_enable: bool = "true",
-        /// Enables highlighting of all break points for a loop or block context while the cursor is on any `async` or `await` keywords.
+        /// Enables highlighting of the intra-doc links to the item under the cursor, like `[Foo]` in a
+        /// doc comment.
+        highlightRelated_references_inStrings: bool = "false",
+        /// Whether to highlight the declaration of the item under the cursor along with its references.
+        highlightRelated_references_includeDeclaration: bool = "true",
+        /// Maximum number of references to search for when highlighting related references. Once reached,
+        /// the search stops and only the references found so far are highlighted.
+        highlightRelated_references_maxCount: Option<usize> = "null",
+        /// Enables highlighting of the implementing types of a trait while the cursor is on the name of
+        /// the trait declaration.
+        highlightRelated_references_traitImpls: bool = "false",
+        /// Enables highlighting of the usages of the return type instead of the exit points while the cursor
+        /// is on the `->` of a function or closure, if the return type is a path to a type.
+        highlightRelated_returnTypeUsages_enable: bool = "false",
+        /// Enables highlighting of all operations requiring `unsafe` inside an unsafe block or function
+        /// while the cursor is on its `unsafe` keyword.
+        highlightRelated_unsafeOperations_enable: bool = "false",
+        /// Only highlights the `.await`s of the statement containing the cursor while it is on an `await`
+        /// keyword, instead of all `.await`s of the async context.
+        highlightRelated_yieldPoints_chainOnly: bool = "false",
+        /// Enables highlighting of all yield and await points of an async or coroutine context while the cursor is on any `async`, `await` or `yield` keywords.
+        /// The point under the cursor is reported as a read highlight, the others as text highlights.
         highlightRelated_yieldPoints_enable: bool = "true",
+        /// Also highlights the exit points of the async context while the cursor is on an `await` keyword.
+        highlightRelated_yieldPoints_includeExitPoints: bool = "false",
+        /// Skips `.await`s on futures that are known to be ready immediately, like `std::future::Ready`,
+        /// when highlighting yield points.
+        highlightRelated_yieldPoints_skipReady: bool = "false",
 
         /// Whether to show `Debug` action. Only applies when
         /// `#rust-analyzer.hover.actions.enable#` is set.
@@ -1726,6 +1804,33 @@ impl Config {
             exit_points: self.data.highlightRelated_exitPoints_enable,
             yield_points: self.data.highlightRelated_yieldPoints_enable,
             closure_captures: self.data.highlightRelated_closureCaptures_enable,
+            break_points_include_header: self.data.highlightRelated_breakPoints_includeHeader,
+            binding_origins: self.data.highlightRelated_bindingOrigins_enable,
+            closure_captures_in_body: self.data.highlightRelated_closureCaptures_inBody,
+            matching_brackets: self.data.highlightRelated_matchingBrackets_enable,
+            max_references: self.data.highlightRelated_references_maxCount,
+            exit_points_distinguish_try: self.data.highlightRelated_exitPoints_distinguishTry,
+            yield_points_skip_ready: self.data.highlightRelated_yieldPoints_skipReady,
+            trait_impls: self.data.highlightRelated_references_traitImpls,
+            exclude_declaration: !self.data.highlightRelated_references_includeDeclaration,
+            params_on_fn: self.data.highlightRelated_exitPoints_params,
+            unsafe_operations: self.data.highlightRelated_unsafeOperations_enable,
+            return_type_usages: self.data.highlightRelated_returnTypeUsages_enable,
+            drop_points: self.data.highlightRelated_dropPoints_enable,
+            exit_points_full_expr: self.data.highlightRelated_exitPoints_fullExpression,
+            yield_points_chain_only: self.data.highlightRelated_yieldPoints_chainOnly,
+            yield_points_with_exits: self.data.highlightRelated_yieldPoints_includeExitPoints,
+            match_arms: self.data.highlightRelated_matchArms_enable,
+            break_value_sources: self.data.highlightRelated_breakValueSources_enable,
+            exit_points_in_branch: self.data.highlightRelated_exitPoints_branchOnly,
+            references_in_strings: self.data.highlightRelated_references_inStrings,
+            exit_points_show_skipped: self.data.highlightRelated_exitPoints_showSkipped,
+            references_adjacent_idents: self.data.highlightRelated_references_adjacentIdentifiers,
+            exit_points_highlight_try_operand: self.data.highlightRelated_exitPoints_tryOperand,
+            discriminant_uses: self.data.highlightRelated_discriminantUses_enable,
+            references_all_macro_arms: self.data.highlightRelated_references_allMacroArms,
+            exclude_builtin_types: !self.data.highlightRelated_references_builtinTypes,
+            highlight_recursion: self.data.highlightRelated_recursion_enable,
         }
     }
 
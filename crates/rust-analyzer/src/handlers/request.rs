@@ -1364,9 +1364,9 @@ pub(crate) fn handle_document_highlight(
     };
     let res = refs
         .into_iter()
-        .map(|ide::HighlightedRange { range, category }| lsp_types::DocumentHighlight {
+        .map(|ide::HighlightedRange { range, category, kind }| lsp_types::DocumentHighlight {
             range: to_proto::range(&line_index, range),
-            kind: category.and_then(to_proto::document_highlight_kind),
+            kind: to_proto::document_highlight_kind(category, kind),
         })
         .collect();
     Ok(Some(res))
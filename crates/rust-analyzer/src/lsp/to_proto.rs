@@ -8,7 +8,7 @@ use std::{
 use ide::{
     Annotation, AnnotationKind, Assist, AssistKind, Cancellable, CompletionItem,
     CompletionItemKind, CompletionRelevance, Documentation, FileId, FileRange, FileSystemEdit,
-    Fold, FoldKind, Highlight, HlMod, HlOperator, HlPunct, HlRange, HlTag, Indel,
+    Fold, FoldKind, Highlight, HighlightKind, HlMod, HlOperator, HlPunct, HlRange, HlTag, Indel,
     InlayFieldsToResolve, InlayHint, InlayHintLabel, InlayHintLabelPart, InlayKind, Markup,
     NavigationTarget, ReferenceCategory, RenameError, Runnable, Severity, SignatureHelp,
     SnippetEdit, SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
@@ -86,12 +86,31 @@ pub(crate) fn structure_node_kind(kind: StructureNodeKind) -> lsp_types::SymbolK
 }
 
 pub(crate) fn document_highlight_kind(
-    category: ReferenceCategory,
+    category: Option<ReferenceCategory>,
+    kind: HighlightKind,
 ) -> Option<lsp_types::DocumentHighlightKind> {
-    match category {
-        ReferenceCategory::Read => Some(lsp_types::DocumentHighlightKind::READ),
-        ReferenceCategory::Write => Some(lsp_types::DocumentHighlightKind::WRITE),
-        ReferenceCategory::Import => None,
+    match kind {
+        HighlightKind::Reference | HighlightKind::Definition | HighlightKind::Capture => {
+            match category? {
+                ReferenceCategory::Read | ReferenceCategory::Move => {
+                    Some(lsp_types::DocumentHighlightKind::READ)
+                }
+                ReferenceCategory::Write => Some(lsp_types::DocumentHighlightKind::WRITE),
+                ReferenceCategory::Import => None,
+            }
+        }
+        // The LSP only knows text, read and write highlights, so the kinds that stand out from the
+        // other highlights of their context are all reported as reads. Clients can neither tell
+        // them apart from each other nor from actual reads.
+        HighlightKind::ConvertingExit | HighlightKind::PrimaryYield | HighlightKind::Recursion => {
+            Some(lsp_types::DocumentHighlightKind::READ)
+        }
+        // The code skipped by an exit is shown along with the exit points, which are text
+        // highlights, and the converting exits, which are reads, so it takes the remaining kind.
+        HighlightKind::Skipped => Some(lsp_types::DocumentHighlightKind::WRITE),
+        HighlightKind::Exit | HighlightKind::Break | HighlightKind::Yield => {
+            Some(lsp_types::DocumentHighlightKind::TEXT)
+        }
     }
 }
 
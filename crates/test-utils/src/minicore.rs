@@ -1188,6 +1188,19 @@ pub mod future {
             self
         }
     }
+
+    pub struct Ready<T>(Option<T>);
+
+    impl<T> Future for Ready<T> {
+        type Output = T;
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+            loop {}
+        }
+    }
+
+    pub fn ready<T>(t: T) -> Ready<T> {
+        Ready(Some(t))
+    }
 }
 pub mod task {
     pub enum Poll<T> {
@@ -1369,6 +1382,36 @@ mod macros {
             /* compiler built-in */
         };
     }
+
+    #[macro_export]
+    macro_rules! todo {
+        () => {
+            $crate::panic!("not yet implemented")
+        };
+        ($($arg:tt)+) => {
+            $crate::panic!($($arg)+)
+        };
+    }
+
+    #[macro_export]
+    macro_rules! unimplemented {
+        () => {
+            $crate::panic!("not implemented")
+        };
+        ($($arg:tt)+) => {
+            $crate::panic!($($arg)+)
+        };
+    }
+
+    #[macro_export]
+    macro_rules! unreachable {
+        () => {
+            $crate::panic!("internal error: entered unreachable code")
+        };
+        ($($arg:tt)+) => {
+            $crate::panic!($($arg)+)
+        };
+    }
     // endregion:panic
 
     // region:asm